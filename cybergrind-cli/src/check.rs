@@ -0,0 +1,44 @@
+use std::{fs, path::PathBuf};
+
+use cybergrind_core::{validate, Map, ParseOptions, Severity};
+
+use crate::Result;
+
+pub fn run(files: &[PathBuf], strict: bool) -> Result<bool> {
+	let mut ok = true;
+	for path in files {
+		let contents = fs::read_to_string(path)
+			.map_err(|e| format!("{}: {}", path.display(), e))?;
+
+		let options = if strict {
+			ParseOptions::strict()
+		} else {
+			ParseOptions::lenient()
+		};
+		let map = match Map::from_str_with(&contents, options) {
+			Ok(map) => map,
+			Err(err) => {
+				// go over the file again to report everything wrong with it, not
+				// just the first problem
+				let (_, mut errors) = Map::from_str_recovering(&contents);
+				if !errors.contains(&err) {
+					errors.push(err);
+				}
+				errors.sort_by_key(|e| (e.line, e.column));
+				for err in errors {
+					println!("{}:{}", path.display(), err);
+				}
+				ok = false;
+				continue;
+			}
+		};
+
+		for diagnostic in validate(&map) {
+			println!("{}: {}", path.display(), diagnostic);
+			if diagnostic.severity == Severity::Error {
+				ok = false;
+			}
+		}
+	}
+	Ok(ok)
+}
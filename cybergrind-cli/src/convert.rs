@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use crate::{
+	format::{Format, ImageOptions},
+	Result,
+};
+
+fn format_of(path: &Path, format: Option<Format>) -> Result<Format> {
+	format.or_else(|| Format::from_path(path)).ok_or_else(|| {
+		format!(
+			"can't tell the format of {} from its extension, use --from or --to",
+			path.display()
+		)
+		.into()
+	})
+}
+
+pub fn run(
+	input: &Path,
+	from: Option<Format>,
+	output: &Path,
+	to: Option<Format>,
+	images: &ImageOptions,
+) -> Result<bool> {
+	let map = format_of(input, from)?.read(input, images)?;
+	format_of(output, to)?.write(&map, output, images)?;
+	Ok(true)
+}
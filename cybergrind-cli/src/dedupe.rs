@@ -0,0 +1,46 @@
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use cybergrind_core::Fingerprint;
+
+use crate::{
+	format::{pattern_files, read_pattern},
+	Result,
+};
+
+pub fn run(dir: &Path, recursive: bool, exact: bool) -> Result<bool> {
+	let mut groups: BTreeMap<Fingerprint, Vec<PathBuf>> = BTreeMap::new();
+	for path in pattern_files(dir, recursive)? {
+		let map = match read_pattern(&path) {
+			Ok(map) => map,
+			Err(err) => {
+				eprintln!("skipping {}", err);
+				continue;
+			}
+		};
+		let fingerprint = if exact {
+			map.fingerprint()
+		} else {
+			map.canonical_fingerprint()
+		};
+		groups.entry(fingerprint).or_default().push(path);
+	}
+
+	let mut duplicates: Vec<_> = groups
+		.into_iter()
+		.filter(|(_, files)| files.len() > 1)
+		.collect();
+	duplicates.sort_by(|(_, a), (_, b)| a[0].cmp(&b[0]));
+	if duplicates.is_empty() {
+		println!("no duplicates");
+	}
+	for (fingerprint, files) in duplicates {
+		println!("{}", fingerprint);
+		for path in files {
+			println!("  {}", path.display());
+		}
+	}
+	Ok(true)
+}
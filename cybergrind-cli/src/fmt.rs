@@ -0,0 +1,22 @@
+use std::{fs, path::PathBuf};
+
+use crate::{format::read_pattern, Result};
+
+pub fn run(files: &[PathBuf], check: bool) -> Result<bool> {
+	let mut ok = true;
+	for path in files {
+		let map = read_pattern(path)?;
+		let formatted = map.to_string();
+		if fs::read_to_string(path)? == formatted {
+			continue;
+		}
+		if check {
+			println!("{} is not formatted", path.display());
+			ok = false;
+		} else {
+			fs::write(path, formatted)?;
+			println!("formatted {}", path.display());
+		}
+	}
+	Ok(ok)
+}
@@ -0,0 +1,280 @@
+use std::{
+	convert::TryFrom,
+	fmt,
+	fs::{self, File},
+	io::{BufReader, BufWriter},
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use cybergrind_core::{
+	image, mesh, GrayScale, Grid, Height, Map, Parsable, SvgOptions,
+};
+use structopt::StructOpt;
+
+use crate::Result;
+
+/// File formats a [`Map`] can be read from or written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+	/// The game's own text format.
+	Pattern,
+	/// A grayscale PNG of the heights. Prefabs go in a color-coded PNG next
+	/// to it, see [`prefab_layer_path`].
+	Heightmap,
+	/// A Wavefront OBJ model, with its colors in an MTL file next to it.
+	/// Write only.
+	Obj,
+	/// A binary glTF model. Write only.
+	Glb,
+	/// A diagram of the grid. Write only.
+	Svg,
+	/// The map as JSON, with heights as plain numbers.
+	Json,
+	/// The map as TOML, with heights as plain numbers.
+	Toml,
+	/// The heights as CSV, with prefabs in another CSV next to it, see
+	/// [`prefab_layer_path`].
+	Csv,
+}
+
+// How maps are drawn by the formats that are images.
+#[derive(Debug, Clone, Copy, StructOpt)]
+pub struct ImageOptions {
+	/// Width in pixels of each tile of images written, at most 256
+	#[structopt(long, default_value = "1")]
+	pub tile_size: u32,
+	/// Height drawn black in heightmaps
+	#[structopt(long, default_value = "-50", allow_hyphen_values = true)]
+	pub black: i8,
+	/// Height drawn white in heightmaps
+	#[structopt(long, default_value = "50", allow_hyphen_values = true)]
+	pub white: i8,
+}
+
+impl Default for ImageOptions {
+	fn default() -> Self {
+		Self {
+			tile_size: 1,
+			black: -50,
+			white: 50,
+		}
+	}
+}
+
+impl ImageOptions {
+	fn gray_scale(&self) -> Result<GrayScale> {
+		Ok(GrayScale {
+			black: Height::try_from(self.black)?,
+			white: Height::try_from(self.white)?,
+		})
+	}
+}
+
+impl Format {
+	pub fn from_path(path: &Path) -> Option<Self> {
+		match path.extension()?.to_str()? {
+			"cgp" | "gcp" | "txt" => Some(Format::Pattern),
+			"png" => Some(Format::Heightmap),
+			"obj" => Some(Format::Obj),
+			"glb" => Some(Format::Glb),
+			"svg" => Some(Format::Svg),
+			"json" => Some(Format::Json),
+			"toml" => Some(Format::Toml),
+			"csv" => Some(Format::Csv),
+			_ => None,
+		}
+	}
+
+	pub fn read(self, path: &Path, images: &ImageOptions) -> Result<Map> {
+		match self {
+			Format::Pattern => read_pattern(path),
+			Format::Heightmap => read_heightmap(path, images),
+			Format::Json => serde_json::from_str(&read_text(path)?)
+				.map_err(|e| format!("{}: {}", path.display(), e).into()),
+			Format::Toml => toml::from_str(&read_text(path)?)
+				.map_err(|e| format!("{}: {}", path.display(), e).into()),
+			Format::Csv => read_csv(path),
+			Format::Obj | Format::Glb | Format::Svg => {
+				Err(format!("can't read maps from {} files", self).into())
+			}
+		}
+	}
+
+	pub fn write(
+		self,
+		map: &Map,
+		path: &Path,
+		images: &ImageOptions,
+	) -> Result<()> {
+		match self {
+			Format::Pattern => fs::write(path, map.to_string())?,
+			Format::Heightmap => write_heightmap(map, path, images)?,
+			Format::Obj => write_obj(map, path)?,
+			Format::Glb => {
+				mesh::write_glb(&map.to_meshes(), BufWriter::new(File::create(path)?))?
+			}
+			Format::Svg => fs::write(path, map.to_svg(&SvgOptions::default()))?,
+			Format::Json => fs::write(path, serde_json::to_string(map)?)?,
+			Format::Toml => fs::write(path, toml::to_string(map)?)?,
+			Format::Csv => {
+				fs::write(path, map.heights.to_csv())?;
+				fs::write(prefab_layer_path(path), map.prefabs.to_csv())?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Display for Format {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			Format::Pattern => "cgp",
+			Format::Heightmap => "png",
+			Format::Obj => "obj",
+			Format::Glb => "glb",
+			Format::Svg => "svg",
+			Format::Json => "json",
+			Format::Toml => "toml",
+			Format::Csv => "csv",
+		})
+	}
+}
+
+impl FromStr for Format {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, String> {
+		match s {
+			"cgp" | "pattern" => Ok(Format::Pattern),
+			"png" | "heightmap" => Ok(Format::Heightmap),
+			"obj" => Ok(Format::Obj),
+			"glb" => Ok(Format::Glb),
+			"svg" => Ok(Format::Svg),
+			"json" => Ok(Format::Json),
+			"toml" => Ok(Format::Toml),
+			"csv" => Ok(Format::Csv),
+			_ => Err(format!(
+				"unknown format `{}`, expected one of: cgp, png, obj, glb, svg, json, toml, csv",
+				s
+			)),
+		}
+	}
+}
+
+fn read_text(path: &Path) -> Result<String> {
+	Ok(
+		fs::read_to_string(path)
+			.map_err(|e| format!("{}: {}", path.display(), e))?,
+	)
+}
+
+/// Reads a pattern file, with any parse error pointing into it.
+pub fn read_pattern(path: &Path) -> Result<Map> {
+	let contents = read_text(path)?;
+	Ok(
+		Map::from_str(&contents)
+			.map_err(|e| format!("{}:{}", path.display(), e))?,
+	)
+}
+
+/// Pattern files in `dir`, and its subdirectories if `recursive`, sorted by
+/// path.
+pub fn pattern_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+	for entry in
+		fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?
+	{
+		let path = entry?.path();
+		if path.is_dir() {
+			if recursive {
+				files.extend(pattern_files(&path, recursive)?);
+			}
+		} else if Format::from_path(&path) == Some(Format::Pattern) {
+			files.push(path);
+		}
+	}
+	files.sort();
+	Ok(files)
+}
+
+/// Where the prefabs of a heightmap or CSV file are kept: `arena.png` has its
+/// prefabs in `arena.prefabs.png`, `arena.csv` in `arena.prefabs.csv`.
+pub fn prefab_layer_path(path: &Path) -> PathBuf {
+	let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+	match path.extension() {
+		Some(ext) => {
+			path.with_file_name(format!("{}.prefabs.{}", stem, ext.to_string_lossy()))
+		}
+		None => path.with_file_name(format!("{}.prefabs", stem)),
+	}
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+	Ok(BufReader::new(
+		File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+	))
+}
+
+/// Reads a heightmap, and its prefab layer if there is one.
+fn read_heightmap(path: &Path, images: &ImageOptions) -> Result<Map> {
+	let heights = image::read_heights(open(path)?, &images.gray_scale()?)
+		.map_err(|e| format!("{}: {}", path.display(), e))?;
+	let layer = prefab_layer_path(path);
+	let prefabs = if layer.exists() {
+		image::read_prefabs(open(&layer)?)
+			.map_err(|e| format!("{}: {}", layer.display(), e))?
+	} else {
+		Parsable::default()
+	};
+	Ok(Map { heights, prefabs })
+}
+
+/// Reads the heights of a CSV file, and its prefabs if there are any.
+fn read_csv(path: &Path) -> Result<Map> {
+	let heights = Grid::from_csv(&read_text(path)?)
+		.map_err(|e| format!("{}:{}", path.display(), e))?;
+	let layer = prefab_layer_path(path);
+	let prefabs = if layer.exists() {
+		Grid::from_csv(&read_text(&layer)?)
+			.map_err(|e| format!("{}:{}", layer.display(), e))?
+	} else {
+		Parsable::default()
+	};
+	Ok(Map { heights, prefabs })
+}
+
+/// Writes a heightmap and its prefab layer.
+fn write_heightmap(
+	map: &Map,
+	path: &Path,
+	images: &ImageOptions,
+) -> Result<()> {
+	image::write_heights(
+		&map.heights,
+		&images.gray_scale()?,
+		images.tile_size,
+		BufWriter::new(File::create(path)?),
+	)?;
+	image::write_prefabs(
+		&map.prefabs,
+		images.tile_size,
+		BufWriter::new(File::create(prefab_layer_path(path))?),
+	)?;
+	Ok(())
+}
+
+/// Writes an OBJ model and its material library, `arena.obj` getting
+/// `arena.mtl`.
+fn write_obj(map: &Map, path: &Path) -> Result<()> {
+	let meshes = map.to_meshes();
+	let mtl = path.with_extension("mtl");
+	let mtl_name = mtl.file_name().unwrap_or_default().to_string_lossy();
+	mesh::write_obj(
+		&meshes,
+		Some(&mtl_name),
+		BufWriter::new(File::create(path)?),
+	)?;
+	mesh::write_mtl(&meshes, BufWriter::new(File::create(&mtl)?))?;
+	Ok(())
+}
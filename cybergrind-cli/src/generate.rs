@@ -0,0 +1,124 @@
+use std::{
+	path::PathBuf,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use cybergrind_core::{
+	generate, GenerateOptions, PrefabCounts, SymmetryMode, Wfc, WfcOptions,
+};
+use structopt::StructOpt;
+
+use crate::{
+	format::{read_pattern, Format, ImageOptions},
+	Result,
+};
+
+// Everything the generate command takes.
+#[derive(StructOpt)]
+pub struct Options {
+	/// Seed of the random arena, a new one if not given
+	#[structopt(long)]
+	seed: Option<u64>,
+	/// `none`, `mirror-x`, `mirror-y`, `four-way` or `rotational`
+	#[structopt(long, default_value = "mirror-x", parse(try_from_str = parse_symmetry))]
+	symmetry: SymmetryMode,
+	/// Rough size in tiles of hills and valleys
+	#[structopt(long, default_value = "6")]
+	scale: f32,
+	/// Layers of finer noise, for rougher terrain, at most 8
+	#[structopt(long, default_value = "2")]
+	octaves: u32,
+	#[structopt(long, default_value = "0", allow_hyphen_values = true)]
+	min_height: i8,
+	#[structopt(long, default_value = "10", allow_hyphen_values = true)]
+	max_height: i8,
+	/// Round heights to multiples of this
+	#[structopt(long, default_value = "2")]
+	step: i8,
+	/// Share of tiles that are pits, from 0 to 1
+	#[structopt(long, default_value = "0.05")]
+	pits: f32,
+	#[structopt(long, default_value = "3")]
+	melee: usize,
+	#[structopt(long, default_value = "3")]
+	projectile: usize,
+	#[structopt(long, default_value = "1")]
+	jump_pads: usize,
+	#[structopt(long, default_value = "2")]
+	stairs: usize,
+	/// Place a Hideous Mass
+	#[structopt(long)]
+	hideous: bool,
+	/// Make the arena in the style of these patterns instead, by wave
+	/// function collapse. Only the seed applies then
+	#[structopt(long, number_of_values = 1)]
+	like: Vec<PathBuf>,
+	/// Width of the bits learned from --like patterns, 2 or 3
+	#[structopt(long, default_value = "3")]
+	pattern_size: usize,
+	/// Where to write the arena, printed if not given
+	output: Option<PathBuf>,
+}
+
+fn parse_symmetry(s: &str) -> std::result::Result<SymmetryMode, String> {
+	match s {
+		"none" => Ok(SymmetryMode::None),
+		"mirror-x" => Ok(SymmetryMode::MirrorX),
+		"mirror-y" => Ok(SymmetryMode::MirrorY),
+		"four-way" => Ok(SymmetryMode::FourWay),
+		"rotational" => Ok(SymmetryMode::Rotational),
+		_ => Err(format!(
+			"unknown symmetry `{}`, expected one of: none, mirror-x, mirror-y, four-way, rotational",
+			s
+		)),
+	}
+}
+
+pub fn run(options: Options) -> Result<bool> {
+	let seed = options.seed.unwrap_or_else(|| {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+		now.as_secs() ^ now.subsec_nanos() as u64
+	});
+	let map = if options.like.is_empty() {
+		generate(&GenerateOptions {
+			seed,
+			symmetry: options.symmetry,
+			scale: options.scale,
+			octaves: options.octaves,
+			min_height: options.min_height,
+			max_height: options.max_height,
+			step: options.step,
+			pit_density: options.pits,
+			prefabs: PrefabCounts {
+				melee: options.melee,
+				projectile: options.projectile,
+				jump_pads: options.jump_pads,
+				stairs: options.stairs,
+				hideous: options.hideous,
+			},
+		})
+	} else {
+		let examples = options
+			.like
+			.iter()
+			.map(|path| read_pattern(path))
+			.collect::<Result<Vec<_>>>()?;
+		Wfc::learn(
+			&examples,
+			&WfcOptions {
+				size: options.pattern_size,
+				..WfcOptions::default()
+			},
+		)?
+		.generate(seed)?
+	};
+	eprintln!("seed {}", seed);
+	match options.output {
+		Some(path) => {
+			let format = Format::from_path(&path).unwrap_or(Format::Pattern);
+			format.write(&map, &path, &ImageOptions::default())?;
+		}
+		None => println!("{}", map),
+	}
+	Ok(true)
+}
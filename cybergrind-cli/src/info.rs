@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use cybergrind_core::{validate, Movement, Prefab, Severity};
+
+use crate::{format::read_pattern, Result};
+
+pub fn run(path: &Path) -> Result<bool> {
+	let map = read_pattern(path)?;
+
+	let heights: Vec<i8> = map.heights.iter().map(|(_, h)| h.get()).collect();
+	let min = heights.iter().min().unwrap();
+	let max = heights.iter().max().unwrap();
+	let mean = heights.iter().map(|h| *h as f32).sum::<f32>() / 256.0;
+	println!("heights:   {} to {}, {:.1} on average", min, max, mean);
+	println!(
+		"pits:      {} tiles",
+		map.heights.iter().filter(|(_, h)| h.is_pit()).count()
+	);
+
+	let count =
+		|prefab: Prefab| map.prefabs.iter().filter(|(_, p)| **p == prefab).count();
+	let mut prefabs = format!(
+		"prefabs:   {} melee, {} projectile, {} jump pad, {} stairs, {} hideous",
+		count(Prefab::Melee),
+		count(Prefab::Projectile),
+		count(Prefab::JumpPad),
+		count(Prefab::Stairs),
+		count(Prefab::Hideous),
+	);
+	let unknown = map.prefabs.iter().filter(|(_, p)| p.is_unknown()).count();
+	if unknown > 0 {
+		prefabs += &format!(", {} unknown", unknown);
+	}
+	println!("{}", prefabs);
+
+	let components = map.walkability(&Movement::default()).components();
+	let sizes: Vec<String> =
+		components.iter().map(|c| c.len().to_string()).collect();
+	println!(
+		"areas:     {} ({} tiles)",
+		components.len(),
+		sizes.join(", ")
+	);
+
+	let diagnostics = validate(&map);
+	let errors = diagnostics
+		.iter()
+		.filter(|d| d.severity == Severity::Error)
+		.count();
+	println!(
+		"lints:     {} errors, {} warnings",
+		errors,
+		diagnostics.len() - errors
+	);
+	Ok(true)
+}
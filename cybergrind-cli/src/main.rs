@@ -0,0 +1,215 @@
+use std::{path::PathBuf, process};
+
+use cybergrind_core::{AnsiOptions, DistanceOptions, RenderOptions, View};
+use structopt::StructOpt;
+
+mod check;
+mod convert;
+mod dedupe;
+mod fmt;
+mod format;
+mod generate;
+mod info;
+mod pack;
+mod render;
+mod show;
+mod similar;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(StructOpt)]
+#[structopt(
+	name = "cybergrind",
+	about = "Works with Cyber Grind pattern files"
+)]
+enum Command {
+	/// Parses and lints patterns, failing if any of them have errors
+	Check {
+		/// Only accept files exactly as the editor writes them
+		#[structopt(long)]
+		strict: bool,
+		#[structopt(required = true)]
+		files: Vec<PathBuf>,
+	},
+	/// Rewrites patterns in their canonical form
+	Fmt {
+		/// Don't write anything, fail if a file isn't formatted
+		#[structopt(long)]
+		check: bool,
+		#[structopt(required = true)]
+		files: Vec<PathBuf>,
+	},
+	/// Prints statistics about a pattern
+	Info { file: PathBuf },
+	/// Draws a pattern in the terminal
+	Show {
+		/// Format of the input, guessed from its extension if not given
+		#[structopt(long)]
+		from: Option<format::Format>,
+		#[structopt(flatten)]
+		images: format::ImageOptions,
+		/// Don't color tiles by height
+		#[structopt(long)]
+		no_color: bool,
+		/// Only draw prefabs, not the height of each tile
+		#[structopt(long)]
+		no_numbers: bool,
+		#[structopt(long)]
+		no_legend: bool,
+		file: PathBuf,
+	},
+	/// Draws a pattern to a PNG image
+	Render {
+		/// Format of the input, guessed from its extension if not given
+		#[structopt(long)]
+		from: Option<format::Format>,
+		/// `top-down` or `isometric`
+		#[structopt(long, default_value = "top-down", parse(try_from_str = parse_view))]
+		view: View,
+		/// Width in pixels of each tile, at most 256
+		#[structopt(long, default_value = "16")]
+		size: u32,
+		#[structopt(long)]
+		no_prefabs: bool,
+		input: PathBuf,
+		output: PathBuf,
+	},
+	/// Converts a pattern between file formats
+	Convert {
+		/// Format of the input, guessed from its extension if not given
+		#[structopt(long)]
+		from: Option<format::Format>,
+		/// Format of the output, guessed from its extension if not given
+		#[structopt(long)]
+		to: Option<format::Format>,
+		#[structopt(flatten)]
+		images: format::ImageOptions,
+		input: PathBuf,
+		output: PathBuf,
+	},
+	/// Groups the patterns in a directory that are copies of each other,
+	/// even turned or mirrored
+	Dedupe {
+		/// Also look in subdirectories
+		#[structopt(short, long)]
+		recursive: bool,
+		/// Only group exact copies
+		#[structopt(long)]
+		exact: bool,
+		dir: PathBuf,
+	},
+	/// Lists the patterns in a directory most like the given one
+	Similar {
+		/// Also look in subdirectories
+		#[structopt(short, long)]
+		recursive: bool,
+		/// How many patterns to list
+		#[structopt(short = "n", long, default_value = "10")]
+		count: usize,
+		/// How much a tile with a different prefab counts, against a height
+		/// difference of one
+		#[structopt(long, default_value = "4")]
+		prefab_weight: u32,
+		/// Don't try turning or mirroring the patterns to line them up
+		#[structopt(long)]
+		exact: bool,
+		file: PathBuf,
+		dir: PathBuf,
+	},
+	/// Makes a random arena
+	Generate(generate::Options),
+	/// Works with packs of many patterns
+	Pack(pack::Command),
+}
+
+fn parse_view(s: &str) -> std::result::Result<View, String> {
+	match s {
+		"top-down" => Ok(View::TopDown),
+		"isometric" => Ok(View::Isometric),
+		_ => Err(format!(
+			"unknown view `{}`, expected top-down or isometric",
+			s
+		)),
+	}
+}
+
+fn main() {
+	let result = match Command::from_args() {
+		Command::Check { strict, files } => check::run(&files, strict),
+		Command::Fmt { check, files } => fmt::run(&files, check),
+		Command::Info { file } => info::run(&file),
+		Command::Show {
+			from,
+			images,
+			no_color,
+			no_numbers,
+			no_legend,
+			file,
+		} => show::run(
+			&file,
+			from,
+			&images,
+			&AnsiOptions {
+				color: !no_color,
+				numbers: !no_numbers,
+				legend: !no_legend,
+			},
+		),
+		Command::Render {
+			from,
+			view,
+			size,
+			no_prefabs,
+			input,
+			output,
+		} => render::run(
+			&input,
+			from,
+			&output,
+			&RenderOptions {
+				view,
+				tile_size: size,
+				prefabs: !no_prefabs,
+			},
+		),
+		Command::Convert {
+			from,
+			to,
+			images,
+			input,
+			output,
+		} => convert::run(&input, from, &output, to, &images),
+		Command::Dedupe {
+			recursive,
+			exact,
+			dir,
+		} => dedupe::run(&dir, recursive, exact),
+		Command::Similar {
+			recursive,
+			count,
+			prefab_weight,
+			exact,
+			file,
+			dir,
+		} => similar::run(
+			&file,
+			&dir,
+			recursive,
+			count,
+			&DistanceOptions {
+				prefab_weight,
+				symmetries: !exact,
+			},
+		),
+		Command::Generate(options) => generate::run(options),
+		Command::Pack(command) => pack::run(command),
+	};
+	match result {
+		Ok(true) => {}
+		Ok(false) => process::exit(1),
+		Err(err) => {
+			eprintln!("error: {}", err);
+			process::exit(2);
+		}
+	}
+}
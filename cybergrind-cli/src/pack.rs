@@ -0,0 +1,134 @@
+use std::{
+	fs::File,
+	io::{BufReader, BufWriter},
+	path::{Path, PathBuf},
+};
+
+use cybergrind_core::{
+	Metadata, Pack, PackEntry, PatternDocument, RenderOptions, View,
+};
+use structopt::StructOpt;
+
+use crate::{format::read_pattern, Result};
+
+#[derive(StructOpt)]
+pub enum Command {
+	/// Bundles patterns, their metadata and thumbnails into a pack
+	Create {
+		/// Leave out the thumbnails
+		#[structopt(long)]
+		no_thumbnails: bool,
+		output: PathBuf,
+		#[structopt(required = true)]
+		files: Vec<PathBuf>,
+	},
+	/// Lists the patterns in a pack
+	List { pack: PathBuf },
+	/// Writes patterns from a pack into a directory
+	Extract {
+		/// Directory to write them to
+		#[structopt(short, long, default_value = ".")]
+		output: PathBuf,
+		pack: PathBuf,
+		/// Names of the patterns to extract, all of them if none are given
+		names: Vec<String>,
+	},
+}
+
+/// Thumbnails are small top-down renders.
+const THUMBNAIL: RenderOptions = RenderOptions {
+	view: View::TopDown,
+	tile_size: 4,
+	prefabs: true,
+};
+
+pub fn run(command: Command) -> Result<bool> {
+	match command {
+		Command::Create {
+			no_thumbnails,
+			output,
+			files,
+		} => create(&output, &files, !no_thumbnails),
+		Command::List { pack } => list(&pack),
+		Command::Extract {
+			output,
+			pack,
+			names,
+		} => extract(&pack, &output, &names),
+	}
+}
+
+fn read(path: &Path) -> Result<Pack> {
+	let file =
+		File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+	Ok(
+		Pack::read(BufReader::new(file))
+			.map_err(|e| format!("{}: {}", path.display(), e))?,
+	)
+}
+
+fn create(output: &Path, files: &[PathBuf], thumbnails: bool) -> Result<bool> {
+	let mut pack = Pack::new();
+	for path in files {
+		let map = read_pattern(path)?;
+		let metadata =
+			Metadata::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+		let thumbnail = if thumbnails {
+			let mut png = Vec::new();
+			map.render(&THUMBNAIL).write_png(&mut png)?;
+			Some(png)
+		} else {
+			None
+		};
+		let name = path.file_stem().unwrap_or_default().to_string_lossy();
+		let replaced = pack.add(PackEntry {
+			name: name.to_string(),
+			document: PatternDocument { map, metadata },
+			thumbnail,
+		})?;
+		if replaced.is_some() {
+			return Err(format!("more than one pattern is named `{}`", name).into());
+		}
+	}
+	pack.write(BufWriter::new(File::create(output)?))?;
+	Ok(true)
+}
+
+fn list(path: &Path) -> Result<bool> {
+	for entry in read(path)?.entries() {
+		let metadata = &entry.document.metadata;
+		let mut line = entry.name.clone();
+		if !metadata.title.is_empty() {
+			line += &format!(": {}", metadata.title);
+		}
+		if !metadata.author.is_empty() {
+			line += &format!(" by {}", metadata.author);
+		}
+		if !metadata.tags.is_empty() {
+			line += &format!(" [{}]", metadata.tags.join(", "));
+		}
+		println!("{}", line);
+	}
+	Ok(true)
+}
+
+fn extract(path: &Path, output: &Path, names: &[String]) -> Result<bool> {
+	let pack = read(path)?;
+	if names.is_empty() {
+		pack.extract(output)?;
+		return Ok(true);
+	}
+	let entries = names
+		.iter()
+		.map(|name| {
+			pack.get(name).ok_or_else(|| {
+				format!("{}: no pattern named `{}`", path.display(), name)
+			})
+		})
+		.collect::<std::result::Result<Vec<_>, _>>()?;
+	std::fs::create_dir_all(output)?;
+	for entry in entries {
+		entry.extract(output)?;
+	}
+	Ok(true)
+}
@@ -0,0 +1,24 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use cybergrind_core::RenderOptions;
+
+use crate::{
+	format::{Format, ImageOptions},
+	Result,
+};
+
+pub fn run(
+	input: &Path,
+	from: Option<Format>,
+	output: &Path,
+	options: &RenderOptions,
+) -> Result<bool> {
+	let format = from
+		.or_else(|| Format::from_path(input))
+		.unwrap_or(Format::Pattern);
+	let map = format.read(input, &ImageOptions::default())?;
+	map
+		.render(options)
+		.write_png(BufWriter::new(File::create(output)?))?;
+	Ok(true)
+}
@@ -0,0 +1,26 @@
+use std::{env, path::Path};
+
+use cybergrind_core::AnsiOptions;
+
+use crate::{
+	format::{Format, ImageOptions},
+	Result,
+};
+
+pub fn run(
+	path: &Path,
+	format: Option<Format>,
+	images: &ImageOptions,
+	options: &AnsiOptions,
+) -> Result<bool> {
+	let format = format
+		.or_else(|| Format::from_path(path))
+		.unwrap_or(Format::Pattern);
+	let map = format.read(path, images)?;
+	let options = AnsiOptions {
+		color: options.color && env::var_os("NO_COLOR").is_none(),
+		..*options
+	};
+	print!("{}", map.to_ansi(&options));
+	Ok(true)
+}
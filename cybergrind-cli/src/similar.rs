@@ -0,0 +1,42 @@
+use std::{fs, path::Path};
+
+use cybergrind_core::{rank, DistanceOptions};
+
+use crate::{
+	format::{pattern_files, read_pattern},
+	Result,
+};
+
+pub fn run(
+	target: &Path,
+	dir: &Path,
+	recursive: bool,
+	count: usize,
+	options: &DistanceOptions,
+) -> Result<bool> {
+	let map = read_pattern(target)?;
+	let target = fs::canonicalize(target)?;
+	let mut candidates = Vec::new();
+	for path in pattern_files(dir, recursive)? {
+		if fs::canonicalize(&path).ok().as_ref() == Some(&target) {
+			continue;
+		}
+		match read_pattern(&path) {
+			Ok(map) => candidates.push((path, map)),
+			Err(err) => eprintln!("skipping {}", err),
+		}
+	}
+
+	println!("distance  heights  prefabs  symmetry        file");
+	for (path, distance) in rank(&map, candidates, options).iter().take(count) {
+		println!(
+			"{:>8}  {:>7}  {:>7}  {:<14}  {}",
+			distance.total,
+			distance.heights,
+			distance.prefabs,
+			format!("{:?}", distance.symmetry),
+			path.display()
+		);
+	}
+	Ok(true)
+}
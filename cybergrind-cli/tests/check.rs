@@ -0,0 +1,38 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+use cybergrind_core::{Height, Map, Parsable, Prefab};
+
+fn check(name: &str, map: &Map) -> bool {
+	let path: PathBuf = env::temp_dir().join(format!(
+		"cybergrind-check-{}-{}.cgp",
+		name,
+		std::process::id()
+	));
+	fs::write(&path, map.to_string()).unwrap();
+	let status = Command::new(env!("CARGO_BIN_EXE_cybergrind"))
+		.arg("check")
+		.arg(&path)
+		.output()
+		.unwrap()
+		.status;
+	fs::remove_file(&path).unwrap();
+	status.success()
+}
+
+#[test]
+fn fails_on_lint_errors() {
+	assert!(check("clean", &Map::default()));
+
+	let mut warned = Map::default();
+	warned.prefabs.0[4][4] = Prefab::Stairs;
+	assert!(check("warned", &warned));
+
+	let mut pit = Map::default();
+	pit.heights.0[0][0] = Height::PIT;
+	pit.prefabs.0[0][0] = Prefab::Melee;
+	assert!(!check("pit", &pit));
+
+	let mut unknown = Map::default();
+	unknown.prefabs.0[0][0] = Prefab::from_char('x').unwrap();
+	assert!(!check("unknown", &unknown));
+}
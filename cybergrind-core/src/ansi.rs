@@ -0,0 +1,179 @@
+//! Drawing patterns in a terminal.
+
+use std::fmt::Write;
+
+use crate::{Height, Map, Prefab};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnsiOptions {
+	/// Color tiles by height. Turn off for terminals without 256 colors.
+	pub color: bool,
+	/// Write each tile's height in it, as well as its prefab.
+	pub numbers: bool,
+	/// Explain the colors and prefab glyphs under the grid.
+	pub legend: bool,
+}
+
+impl Default for AnsiOptions {
+	fn default() -> Self {
+		Self {
+			color: true,
+			numbers: true,
+			legend: true,
+		}
+	}
+}
+
+/// Blue through green to red, as xterm 256-color palette indices.
+const GRADIENT: [u8; 16] = [
+	19, 21, 27, 33, 39, 45, 49, 48, 46, 118, 154, 190, 226, 214, 208, 196,
+];
+const PIT_COLOR: u8 = 236;
+
+/// Picks a color for `height`, spreading the non-pit heights of the map
+/// between `low` and `high` over the whole gradient.
+fn color(height: Height, low: i8, high: i8) -> u8 {
+	if height.is_pit() {
+		return PIT_COLOR;
+	}
+	let span = (high as i32 - low as i32).max(1);
+	let i = (height.0 as i32 - low as i32) * (GRADIENT.len() as i32 - 1) / span;
+	GRADIENT[i.max(0).min(GRADIENT.len() as i32 - 1) as usize]
+}
+
+fn foreground(background: u8) -> u8 {
+	if background == PIT_COLOR || GRADIENT[..4].contains(&background) {
+		255
+	} else {
+		16
+	}
+}
+
+/// The character a prefab is drawn with, which is the same one pattern files
+/// use. Empty tiles are blank.
+pub fn glyph(prefab: Prefab) -> char {
+	match prefab {
+		Prefab::None => ' ',
+		_ => prefab.to_char(),
+	}
+}
+
+fn paint(out: &mut String, options: &AnsiOptions, bg: u8, text: &str) {
+	if options.color {
+		write!(
+			out,
+			"\x1b[48;5;{};38;5;{}m{}\x1b[0m",
+			bg,
+			foreground(bg),
+			text
+		)
+		.unwrap();
+	} else {
+		out.push_str(text);
+	}
+}
+
+/// Renders `map` as text for a terminal, one line per row with column
+/// numbers across the top.
+pub fn render_ansi(map: &Map, options: &AnsiOptions) -> String {
+	let ground: Vec<i8> = map
+		.heights
+		.iter()
+		.filter(|(_, h)| !h.is_pit())
+		.map(|(_, h)| h.0)
+		.collect();
+	let low = ground.iter().copied().min().unwrap_or(0);
+	let high = ground.iter().copied().max().unwrap_or(0);
+	let width = if options.numbers { 4 } else { 2 };
+
+	let mut out = String::new();
+	out.push_str("   ");
+	for x in 0..16 {
+		write!(out, "{:^width$}", x, width = width).unwrap();
+	}
+	out.push('\n');
+
+	for (y, row) in map.heights.0.iter().enumerate() {
+		write!(out, "{:>2} ", y).unwrap();
+		for (x, height) in row.iter().enumerate() {
+			let glyph = glyph(map.prefabs.0[y][x]);
+			let text = if options.numbers {
+				format!("{:>3}{}", height.0, glyph)
+			} else {
+				format!("{} ", glyph)
+			};
+			paint(&mut out, options, color(*height, low, high), &text);
+		}
+		out.push('\n');
+	}
+
+	if options.legend {
+		out.push('\n');
+		if options.color {
+			let mut heights: Vec<Height> =
+				map.heights.iter().map(|(_, h)| *h).collect();
+			heights.sort();
+			heights.dedup();
+			for height in heights {
+				paint(&mut out, options, color(height, low, high), "  ");
+				write!(out, " {}  ", height.0).unwrap();
+			}
+			out.push('\n');
+		}
+		for prefab in [
+			Prefab::Melee,
+			Prefab::Projectile,
+			Prefab::JumpPad,
+			Prefab::Stairs,
+			Prefab::Hideous,
+		]
+		.iter()
+		{
+			write!(out, "{} {:?}  ", glyph(*prefab), prefab).unwrap();
+		}
+		out.push('\n');
+	}
+	out
+}
+
+impl Map {
+	pub fn to_ansi(&self, options: &AnsiOptions) -> String {
+		render_ansi(self, options)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Parsable;
+
+	#[test]
+	fn plain() {
+		let mut map = Map::default();
+		map.heights.0[0][1] = Height(-15);
+		map.prefabs.0[0][2] = Prefab::JumpPad;
+		let options = AnsiOptions {
+			color: false,
+			numbers: true,
+			legend: false,
+		};
+		let rendered = map.to_ansi(&options);
+		let lines: Vec<&str> = rendered.lines().collect();
+		assert_eq!(lines.len(), 17);
+		assert!(lines[0].starts_with("    0   1   2 "));
+		assert!(lines[1].starts_with(" 0   0 -15   0J  0 "));
+		assert_eq!(lines[16].len(), 3 + 16 * 4);
+	}
+
+	#[test]
+	fn colors() {
+		assert_eq!(color(Height(-15), 0, 5), PIT_COLOR);
+		assert_eq!(color(Height(0), 0, 5), GRADIENT[0]);
+		assert_eq!(color(Height(5), 0, 5), GRADIENT[15]);
+		assert_eq!(color(Height(3), 3, 3), GRADIENT[0]);
+
+		let rendered = Map::default().to_ansi(&AnsiOptions::default());
+		assert!(rendered.contains("\x1b[48;5;19;38;5;255m  0 \x1b[0m"));
+		assert!(rendered.contains("J JumpPad"));
+	}
+}
@@ -0,0 +1,72 @@
+//! A git merge driver for pattern files. Set it up with
+//!
+//! ```text
+//! # .gitattributes
+//! *.cgp merge=cybergrind
+//!
+//! # .git/config
+//! [merge "cybergrind"]
+//!     name = cybergrind pattern merge
+//!     driver = cybergrind-merge %O %A %B %P
+//! ```
+//!
+//! The merged pattern is written over `%A`. Tiles that conflict keep our
+//! version and are listed in `<%P>.conflicts`, in which case the driver exits
+//! with 1 so git marks the file as conflicted.
+
+use std::{fs, path::Path, process, str::FromStr};
+
+use cybergrind_core::{merge::merge, Map};
+
+fn read_map(path: &str) -> Result<Map, String> {
+	let contents =
+		fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+	Map::from_str(&contents).map_err(|e| format!("{}:{}", path, e))
+}
+
+fn run(args: &[String]) -> Result<bool, String> {
+	let (base, ours, theirs, name) = match args {
+		[base, ours, theirs] => (base, ours, theirs, ours),
+		[base, ours, theirs, name] => (base, ours, theirs, name),
+		_ => {
+			return Err(
+				"usage: cybergrind-merge <base> <ours> <theirs> [<path>]".to_string(),
+			)
+		}
+	};
+
+	let merged = merge(&read_map(base)?, &read_map(ours)?, &read_map(theirs)?);
+	fs::write(ours, merged.map.to_string())
+		.map_err(|e| format!("{}: {}", ours, e))?;
+
+	let conflicts_path = format!("{}.conflicts", name);
+	if merged.conflicts.is_empty() {
+		if Path::new(&conflicts_path).exists() {
+			fs::remove_file(&conflicts_path)
+				.map_err(|e| format!("{}: {}", conflicts_path, e))?;
+		}
+		Ok(true)
+	} else {
+		fs::write(&conflicts_path, merged.conflicts.to_string())
+			.map_err(|e| format!("{}: {}", conflicts_path, e))?;
+		eprintln!(
+			"{}: {} conflicting tiles, kept ours and listed them in {}",
+			name,
+			merged.conflicts.len(),
+			conflicts_path
+		);
+		Ok(false)
+	}
+}
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	match run(&args) {
+		Ok(true) => {}
+		Ok(false) => process::exit(1),
+		Err(err) => {
+			eprintln!("{}", err);
+			process::exit(2);
+		}
+	}
+}
@@ -0,0 +1,187 @@
+//! Grids as CSV, one row of the grid per line, for spreadsheets and scripts
+//! that would rather not deal with the pattern syntax.
+
+use std::fmt;
+
+use crate::{Grid, Height, Parsable, Prefab};
+
+/// A value that can be written in a CSV cell.
+pub trait CsvCell: Sized {
+	fn to_cell(&self) -> String;
+	fn from_cell(cell: &str) -> Option<Self>;
+}
+
+/// Plain numbers, without the parentheses pattern files put around some.
+impl CsvCell for Height {
+	fn to_cell(&self) -> String {
+		self.0.to_string()
+	}
+
+	fn from_cell(cell: &str) -> Option<Self> {
+		cell.parse().ok().and_then(Height::new)
+	}
+}
+
+/// Written as in pattern files. Reading also takes a known prefab's name, in
+/// any case.
+impl CsvCell for Prefab {
+	fn to_cell(&self) -> String {
+		self.to_string()
+	}
+
+	fn from_cell(cell: &str) -> Option<Self> {
+		let mut chars = cell.chars();
+		if let (Some(c), None) = (chars.next(), chars.next()) {
+			return Prefab::from_char(c);
+		}
+		Prefab::ALL
+			.iter()
+			.copied()
+			.find(|p| format!("{:?}", p).eq_ignore_ascii_case(cell))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvError {
+	/// A cell that isn't a valid value. Lines and columns are 1-based.
+	Cell {
+		line: usize,
+		column: usize,
+		cell: String,
+	},
+	/// A row without exactly 16 cells.
+	RowLength { line: usize, length: usize },
+	/// Not exactly 16 rows.
+	RowCount(usize),
+}
+
+impl fmt::Display for CsvError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			CsvError::Cell { line, column, cell } => {
+				write!(f, "{}:{}: invalid value {:?}", line, column, cell)
+			}
+			CsvError::RowLength { line, length } => {
+				write!(f, "{}: row has {} cells, expected 16", line, length)
+			}
+			CsvError::RowCount(count) => {
+				write!(f, "found {} rows, expected 16", count)
+			}
+		}
+	}
+}
+
+impl std::error::Error for CsvError {}
+
+impl<T: Parsable + Copy + CsvCell> Grid<T> {
+	pub fn to_csv(&self) -> String {
+		let mut out = String::new();
+		for row in self.0.iter() {
+			let cells: Vec<String> = row.iter().map(CsvCell::to_cell).collect();
+			out.push_str(&cells.join(","));
+			out.push('\n');
+		}
+		out
+	}
+
+	/// Reads 16 lines of 16 comma separated cells. Whitespace and quotes
+	/// around cells are ignored, as are blank lines at the end.
+	pub fn from_csv(input: &str) -> Result<Self, CsvError> {
+		let lines: Vec<&str> = input.trim_end().lines().collect();
+		if lines.len() != 16 {
+			return Err(CsvError::RowCount(lines.len()));
+		}
+		let mut grid = Self::default();
+		for (y, line) in lines.iter().enumerate() {
+			let cells: Vec<&str> = line.split(',').collect();
+			if cells.len() != 16 {
+				return Err(CsvError::RowLength {
+					line: y + 1,
+					length: cells.len(),
+				});
+			}
+			for (x, cell) in cells.iter().enumerate() {
+				let cell = cell.trim().trim_matches('"');
+				grid.0[y][x] = T::from_cell(cell).ok_or_else(|| CsvError::Cell {
+					line: y + 1,
+					column: x + 1,
+					cell: cell.to_string(),
+				})?;
+			}
+		}
+		Ok(grid)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Map, UnknownChar};
+
+	#[test]
+	fn round_trip() {
+		let mut map = Map::default();
+		map.heights.0[0][1] = Height(-15);
+		map.heights.0[0][2] = Height(12);
+		map.prefabs.0[0][1] = Prefab::JumpPad;
+
+		let heights = map.heights.to_csv();
+		assert!(heights.starts_with("0,-15,12,0,"));
+		assert_eq!(heights.lines().count(), 16);
+		assert_eq!(Grid::from_csv(&heights), Ok(map.heights));
+
+		let prefabs = map.prefabs.to_csv();
+		assert!(prefabs.starts_with("0,J,0,"));
+		assert_eq!(Grid::from_csv(&prefabs), Ok(map.prefabs));
+	}
+
+	#[test]
+	fn lenient_cells() {
+		let mut csv =
+			String::from("\"jumppad\", Melee ,x,0,0,0,0,0,0,0,0,0,0,0,0,0\r\n");
+		for _ in 1..16 {
+			csv.push_str("0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0\r\n");
+		}
+		csv.push('\n');
+		let prefabs = Grid::<Prefab>::from_csv(&csv).unwrap();
+		assert_eq!(prefabs.get(0, 0), Some(&Prefab::JumpPad));
+		assert_eq!(prefabs.get(1, 0), Some(&Prefab::Melee));
+		assert_eq!(prefabs.get(2, 0), Some(&Prefab::Unknown(UnknownChar('x'))));
+		assert_eq!(Prefab::from_cell("unknown"), None);
+	}
+
+	#[test]
+	fn errors() {
+		let mut heights = Grid::<Height>::default().to_csv();
+		assert_eq!(
+			Grid::<Height>::from_csv(&heights[..heights.len() - 33]),
+			Err(CsvError::RowCount(15))
+		);
+		heights.replace_range(2..3, "x");
+		assert_eq!(
+			Grid::<Height>::from_csv(&heights),
+			Err(CsvError::Cell {
+				line: 1,
+				column: 2,
+				cell: "x".to_string()
+			})
+		);
+		heights.replace_range(2..3, "51");
+		assert_eq!(
+			Grid::<Height>::from_csv(&heights),
+			Err(CsvError::Cell {
+				line: 1,
+				column: 2,
+				cell: "51".to_string()
+			})
+		);
+		heights.replace_range(1..4, "");
+		assert_eq!(
+			Grid::<Height>::from_csv(&heights),
+			Err(CsvError::RowLength {
+				line: 1,
+				length: 15
+			})
+		);
+	}
+}
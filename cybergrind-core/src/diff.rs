@@ -0,0 +1,166 @@
+use std::fmt;
+
+use crate::{Height, Map, Prefab};
+
+/// Everything that changed on a single tile. Each change is a
+/// `(before, after)` pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileChange {
+	pub x: usize,
+	pub y: usize,
+	pub height: Option<(Height, Height)>,
+	pub prefab: Option<(Prefab, Prefab)>,
+}
+
+impl TileChange {
+	pub fn invert(&self) -> Self {
+		Self {
+			height: self.height.map(|(before, after)| (after, before)),
+			prefab: self.prefab.map(|(before, after)| (after, before)),
+			..*self
+		}
+	}
+}
+
+impl fmt::Display for TileChange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "({}, {})", self.x, self.y)?;
+		if let Some((before, after)) = self.height {
+			write!(f, " height {} -> {}", before, after)?;
+		}
+		if let Some((before, after)) = self.prefab {
+			if self.height.is_some() {
+				write!(f, ",")?;
+			}
+			write!(f, " prefab {} -> {}", before, after)?;
+		}
+		Ok(())
+	}
+}
+
+/// The tiles that differ between two maps, in row order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapDiff {
+	pub changes: Vec<TileChange>,
+}
+
+impl MapDiff {
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty()
+	}
+
+	/// Sets every changed tile of `map` to its value after the change.
+	pub fn apply(&self, map: &mut Map) {
+		for change in self.changes.iter() {
+			if let Some((_, after)) = change.height {
+				map.heights.0[change.y][change.x] = after;
+			}
+			if let Some((_, after)) = change.prefab {
+				map.prefabs.0[change.y][change.x] = after;
+			}
+		}
+	}
+
+	/// The diff that undoes this one.
+	pub fn invert(&self) -> Self {
+		Self {
+			changes: self.changes.iter().map(TileChange::invert).collect(),
+		}
+	}
+}
+
+/// One changed tile per line.
+impl fmt::Display for MapDiff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, change) in self.changes.iter().enumerate() {
+			if i > 0 {
+				writeln!(f)?;
+			}
+			write!(f, "{}", change)?;
+		}
+		Ok(())
+	}
+}
+
+impl Map {
+	/// What has to change to turn `self` into `other`.
+	pub fn diff(&self, other: &Map) -> MapDiff {
+		let mut changes = Vec::new();
+		for ((x, y), before) in self.heights.iter() {
+			let after = other.heights.0[y][x];
+			let height = Some((*before, after)).filter(|(b, a)| b != a);
+			let (before, after) = (self.prefabs.0[y][x], other.prefabs.0[y][x]);
+			let prefab = Some((before, after)).filter(|(b, a)| b != a);
+			if height.is_some() || prefab.is_some() {
+				changes.push(TileChange {
+					x,
+					y,
+					height,
+					prefab,
+				});
+			}
+		}
+		MapDiff { changes }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Parsable;
+
+	fn edited() -> (Map, Map) {
+		let before = Map::default();
+		let mut after = before;
+		after.heights.0[1][2] = Height(-15);
+		after.prefabs.0[1][2] = Prefab::JumpPad;
+		after.prefabs.0[4][3] = Prefab::Melee;
+		(before, after)
+	}
+
+	#[test]
+	fn diff() {
+		let (before, after) = edited();
+		let diff = before.diff(&after);
+		assert_eq!(
+			diff.changes,
+			vec![
+				TileChange {
+					x: 2,
+					y: 1,
+					height: Some((Height(0), Height(-15))),
+					prefab: Some((Prefab::None, Prefab::JumpPad)),
+				},
+				TileChange {
+					x: 3,
+					y: 4,
+					height: None,
+					prefab: Some((Prefab::None, Prefab::Melee)),
+				},
+			]
+		);
+		assert!(before.diff(&before).is_empty());
+	}
+
+	#[test]
+	fn apply_and_invert() {
+		let (before, after) = edited();
+		let diff = before.diff(&after);
+
+		let mut map = before;
+		diff.apply(&mut map);
+		assert_eq!(map, after);
+		diff.invert().apply(&mut map);
+		assert_eq!(map, before);
+		assert_eq!(diff.invert(), after.diff(&before));
+	}
+
+	#[test]
+	fn display() {
+		let (before, after) = edited();
+		assert_eq!(
+			before.diff(&after).to_string(),
+			"(2, 1) height 0 -> (-15), prefab 0 -> J\n(3, 4) prefab 0 -> n"
+		);
+	}
+}
@@ -0,0 +1,238 @@
+//! Patterns together with what the `.cgp` format has no room for: who made
+//! them, when and why.
+//!
+//! Metadata is kept in a TOML file next to the pattern, `arena.cgp` having
+//! its metadata in `arena.cgp.meta.toml`. Patterns without one just have
+//! empty metadata.
+
+use std::{
+	fmt, fs, io,
+	path::{Path, PathBuf},
+	str::FromStr,
+	time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Map, ParseError};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+	#[serde(skip_serializing_if = "String::is_empty")]
+	pub title: String,
+	#[serde(skip_serializing_if = "String::is_empty")]
+	pub author: String,
+	#[serde(with = "timestamp", skip_serializing_if = "Option::is_none")]
+	pub created: Option<SystemTime>,
+	#[serde(with = "timestamp", skip_serializing_if = "Option::is_none")]
+	pub modified: Option<SystemTime>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub tags: Vec<String>,
+	#[serde(skip_serializing_if = "String::is_empty")]
+	pub notes: String,
+}
+
+/// Timestamps as RFC 3339 strings, to the second.
+mod timestamp {
+	use std::time::SystemTime;
+
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(
+		time: &Option<SystemTime>,
+		s: S,
+	) -> Result<S::Ok, S::Error> {
+		match time {
+			Some(time) => s.collect_str(&humantime::format_rfc3339_seconds(*time)),
+			None => s.serialize_none(),
+		}
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(
+		d: D,
+	) -> Result<Option<SystemTime>, D::Error> {
+		Option::<String>::deserialize(d)?
+			.map(|s| humantime::parse_rfc3339_weak(&s).map_err(D::Error::custom))
+			.transpose()
+	}
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+	Io(io::Error),
+	Pattern(ParseError),
+	Metadata(toml::de::Error),
+}
+
+impl fmt::Display for DocumentError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			DocumentError::Io(e) => e.fmt(f),
+			DocumentError::Pattern(e) => e.fmt(f),
+			DocumentError::Metadata(e) => write!(f, "invalid metadata: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for DocumentError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			DocumentError::Io(e) => Some(e),
+			DocumentError::Pattern(e) => Some(e),
+			DocumentError::Metadata(e) => Some(e),
+		}
+	}
+}
+
+impl From<io::Error> for DocumentError {
+	fn from(e: io::Error) -> Self {
+		DocumentError::Io(e)
+	}
+}
+
+impl From<ParseError> for DocumentError {
+	fn from(e: ParseError) -> Self {
+		DocumentError::Pattern(e)
+	}
+}
+
+impl From<toml::de::Error> for DocumentError {
+	fn from(e: toml::de::Error) -> Self {
+		DocumentError::Metadata(e)
+	}
+}
+
+/// Where the metadata of the pattern at `path` is kept.
+pub fn metadata_path(path: &Path) -> PathBuf {
+	let mut name = path.file_name().unwrap_or_default().to_os_string();
+	name.push(".meta.toml");
+	path.with_file_name(name)
+}
+
+impl Metadata {
+	pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+		toml::from_str(s)
+	}
+
+	pub fn to_toml(&self) -> String {
+		toml::to_string(self).expect("metadata is always valid TOML")
+	}
+
+	/// Reads the metadata of the pattern at `path`, empty if it has none.
+	pub fn read(path: &Path) -> Result<Self, DocumentError> {
+		match fs::read_to_string(metadata_path(path)) {
+			Ok(s) => Ok(Self::from_toml(&s)?),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Writes the metadata of the pattern at `path`.
+	pub fn write(&self, path: &Path) -> Result<(), DocumentError> {
+		fs::write(metadata_path(path), self.to_toml())?;
+		Ok(())
+	}
+
+	/// Marks the pattern as modified at `now`, and created then if it wasn't
+	/// already.
+	pub fn touch(&mut self, now: SystemTime) {
+		self.created.get_or_insert(now);
+		self.modified = Some(now);
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternDocument {
+	pub map: Map,
+	pub metadata: Metadata,
+}
+
+impl PatternDocument {
+	pub fn new(map: Map) -> Self {
+		Self {
+			map,
+			metadata: Metadata::default(),
+		}
+	}
+
+	/// Reads the pattern at `path` and its metadata.
+	pub fn read(path: &Path) -> Result<Self, DocumentError> {
+		Ok(Self {
+			map: Map::from_str(&fs::read_to_string(path)?)?,
+			metadata: Metadata::read(path)?,
+		})
+	}
+
+	/// Writes the pattern to `path` and its metadata next to it, marking it as
+	/// modified now.
+	pub fn write(&mut self, path: &Path) -> Result<(), DocumentError> {
+		self.metadata.touch(SystemTime::now());
+		fs::write(path, self.map.to_string())?;
+		self.metadata.write(path)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::{Height, Parsable, Prefab};
+
+	#[test]
+	fn sidecar_path() {
+		assert_eq!(
+			metadata_path(Path::new("arenas/arena.cgp")),
+			Path::new("arenas/arena.cgp.meta.toml")
+		);
+	}
+
+	#[test]
+	fn toml() {
+		assert_eq!(Metadata::default().to_toml(), "");
+		assert_eq!(Metadata::from_toml(""), Ok(Metadata::default()));
+
+		let mut metadata = Metadata {
+			title: "Pillars".to_string(),
+			author: "someone".to_string(),
+			tags: vec!["symmetric".to_string(), "pits".to_string()],
+			notes: "Four pillars.\nJump pads in the middle.".to_string(),
+			..Metadata::default()
+		};
+		metadata.touch(SystemTime::UNIX_EPOCH + Duration::from_secs(86400));
+		metadata.touch(SystemTime::UNIX_EPOCH + Duration::from_secs(90000));
+		let toml = metadata.to_toml();
+		assert!(toml.contains("created = \"1970-01-02T00:00:00Z\"\n"));
+		assert!(toml.contains("modified = \"1970-01-02T01:00:00Z\"\n"));
+		assert_eq!(Metadata::from_toml(&toml), Ok(metadata));
+
+		assert!(Metadata::from_toml("created = \"yesterday\"").is_err());
+	}
+
+	#[test]
+	fn read_and_write() {
+		let dir = std::env::temp_dir()
+			.join(format!("cybergrind-document-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("arena.cgp");
+
+		let mut document = PatternDocument::new(Map::default());
+		document.map.heights.0[2][3] = Height(-15);
+		document.map.prefabs.0[2][3] = Prefab::Stairs;
+		document.metadata.title = "Arena".to_string();
+		document.write(&path).unwrap();
+		assert!(document.metadata.created.is_some());
+		let read = PatternDocument::read(&path).unwrap();
+		assert_eq!(read.map, document.map);
+		assert_eq!(read.metadata.title, "Arena");
+		assert!(read.metadata.modified.is_some());
+
+		fs::remove_file(metadata_path(&path)).unwrap();
+		assert_eq!(
+			PatternDocument::read(&path).unwrap().metadata,
+			Metadata::default()
+		);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,263 @@
+//! Operations over whole height fields, for shaping terrain in bulk.
+//!
+//! Every filter can be limited to a [`Mask`] of tiles, the rest being left
+//! as they were. Tiles outside the mask are still read, so smoothing the
+//! edge of a selection blends it into what's around it. Results are always
+//! clamped to the heights the game accepts, see [`Height::clamped`].
+
+use crate::{neighbours, Grid, Height};
+
+/// Which tiles a filter changes, indexed `[y][x]` like a [`Grid`].
+pub type Mask = [[bool; 16]; 16];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+	/// Each tile becomes the mean of the square of tiles `radius` around it.
+	Smooth { radius: usize },
+	/// Each tile becomes the median of the square of tiles `radius` around
+	/// it, which keeps sharp edges better than [`Filter::Smooth`].
+	Median { radius: usize },
+	/// Slopes steeper than `talus` crumble onto the tiles below them, over
+	/// `iterations` passes. The total height is kept the same.
+	Erode { talus: u8, iterations: usize },
+	/// Snaps tiles to `levels` heights evenly spread between the lowest and
+	/// highest tile.
+	Terrace { levels: u8 },
+	/// Rounds tiles to the nearest multiple of `step`.
+	Quantize { step: u8 },
+	/// Stretches tiles so the lowest is at `min` and the highest at `max`.
+	Normalize { min: i8, max: i8 },
+	/// Turns tiles upside down, the lowest swapping places with the highest.
+	Invert,
+}
+
+/// `n / d` rounded to the nearest whole number, halves away from zero.
+fn div_round(n: i32, d: i32) -> i32 {
+	let q = (2 * n.abs() + d.abs()) / (2 * d.abs());
+	if (n < 0) != (d < 0) {
+		-q
+	} else {
+		q
+	}
+}
+
+fn window(
+	grid: &Grid<Height>,
+	(x, y): (usize, usize),
+	radius: usize,
+) -> Vec<i32> {
+	let (x0, y0) = (x.saturating_sub(radius), y.saturating_sub(radius));
+	let (x1, y1) = (
+		x.saturating_add(radius).min(15),
+		y.saturating_add(radius).min(15),
+	);
+	(y0..=y1)
+		.flat_map(|y| (x0..=x1).map(move |x| grid.0[y][x].0 as i32))
+		.collect()
+}
+
+impl Grid<Height> {
+	/// Runs `filter` over the tiles in `mask`, or every tile if there's none.
+	pub fn filter(&self, filter: Filter, mask: Option<&Mask>) -> Self {
+		let masked = |(x, y): (usize, usize)| match mask {
+			Some(mask) => mask[y][x],
+			None => true,
+		};
+		let tiles: Vec<_> = (0..16)
+			.flat_map(|y| (0..16).map(move |x| (x, y)))
+			.filter(|t| masked(*t))
+			.collect();
+		let (low, high) = tiles
+			.iter()
+			.map(|&(x, y)| self.0[y][x].0 as i32)
+			.fold((i32::MAX, i32::MIN), |(l, h), v| (l.min(v), h.max(v)));
+
+		let mut grid = *self;
+		match filter {
+			Filter::Smooth { radius } => {
+				for &(x, y) in &tiles {
+					let values = window(self, (x, y), radius);
+					let sum = values.iter().sum();
+					grid.0[y][x] = Height::clamped(div_round(sum, values.len() as i32));
+				}
+			}
+			Filter::Median { radius } => {
+				for &(x, y) in &tiles {
+					let mut values = window(self, (x, y), radius);
+					values.sort_unstable();
+					grid.0[y][x] = Height::clamped(values[values.len() / 2]);
+				}
+			}
+			Filter::Erode { talus, iterations } => {
+				for _ in 0..iterations {
+					let mut moved = false;
+					for &(x, y) in &tiles {
+						let lowest = neighbours((x, y))
+							.filter(|n| masked(*n))
+							.min_by_key(|&(x, y)| grid.0[y][x]);
+						let (nx, ny) = match lowest {
+							Some(n) => n,
+							None => continue,
+						};
+						let drop = grid.0[y][x].0 as i32 - grid.0[ny][nx].0 as i32;
+						if drop > talus as i32 {
+							let amount = ((drop - talus as i32) / 2).max(1) as i8;
+							grid.0[y][x].0 -= amount;
+							grid.0[ny][nx].0 += amount;
+							moved = true;
+						}
+					}
+					if !moved {
+						break;
+					}
+				}
+			}
+			Filter::Terrace { levels } => {
+				let gaps = levels.max(1) as i32 - 1;
+				for &(x, y) in &tiles {
+					let h = self.0[y][x].0 as i32;
+					grid.0[y][x] = if gaps == 0 || high == low {
+						Height::clamped(low)
+					} else {
+						let level = div_round((h - low) * gaps, high - low);
+						Height::clamped(low + div_round(level * (high - low), gaps))
+					};
+				}
+			}
+			Filter::Quantize { step } => {
+				let step = step.max(1) as i32;
+				for &(x, y) in &tiles {
+					let h = self.0[y][x].0 as i32;
+					grid.0[y][x] = Height::clamped(div_round(h, step) * step);
+				}
+			}
+			Filter::Normalize { min, max } => {
+				let (min, max) = (min as i32, max as i32);
+				for &(x, y) in &tiles {
+					let h = self.0[y][x].0 as i32;
+					grid.0[y][x] = if high == low {
+						Height::clamped(min)
+					} else {
+						Height::clamped(
+							min + div_round((h - low) * (max - min), high - low),
+						)
+					};
+				}
+			}
+			Filter::Invert => {
+				for &(x, y) in &tiles {
+					grid.0[y][x] = Height::clamped(low + high - self.0[y][x].0 as i32);
+				}
+			}
+		}
+		grid
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Parsable;
+
+	fn ramp() -> Grid<Height> {
+		let mut grid = Grid::<Height>::default();
+		for (y, row) in grid.0.iter_mut().enumerate() {
+			for (x, h) in row.iter_mut().enumerate() {
+				*h = Height(x as i8 + y as i8);
+			}
+		}
+		grid
+	}
+
+	fn heights(grid: &Grid<Height>) -> Vec<i8> {
+		grid.iter().map(|(_, h)| h.0).collect()
+	}
+
+	#[test]
+	fn smoothing() {
+		let mut spike = Grid::<Height>::default();
+		spike.0[5][5] = Height(18);
+		let smooth = spike.filter(Filter::Smooth { radius: 1 }, None);
+		assert_eq!(smooth.0[5][5], Height(2));
+		assert_eq!(smooth.0[4][6], Height(2));
+		assert_eq!(smooth.0[5][7], Height(0));
+		let median = spike.filter(Filter::Median { radius: 1 }, None);
+		assert_eq!(median, Grid::<Height>::default());
+		assert_eq!(
+			ramp().filter(Filter::Median { radius: 2 }, None).0[8][8],
+			ramp().0[8][8]
+		);
+		// the whole grid is in reach
+		let whole = ramp().filter(Filter::Smooth { radius: usize::MAX }, None);
+		assert!(whole.iter().all(|(_, h)| *h == Height(15)));
+		let whole = ramp().filter(Filter::Median { radius: usize::MAX }, None);
+		assert!(whole.iter().all(|(_, h)| *h == Height(15)));
+	}
+
+	#[test]
+	fn erosion() {
+		let mut cliff = Grid::<Height>::default();
+		for row in cliff.0.iter_mut() {
+			for h in row[..8].iter_mut() {
+				*h = Height(20);
+			}
+		}
+		let total =
+			|g: &Grid<Height>| g.iter().map(|(_, h)| h.0 as i32).sum::<i32>();
+		let eroded = cliff.filter(
+			Filter::Erode {
+				talus: 2,
+				iterations: 100,
+			},
+			None,
+		);
+		assert_eq!(total(&eroded), total(&cliff));
+		for ((x, y), h) in eroded.iter() {
+			for (nx, ny) in neighbours((x, y)) {
+				assert!((h.0 - eroded.0[ny][nx].0).abs() <= 2);
+			}
+		}
+	}
+
+	#[test]
+	fn levels() {
+		let grid = ramp();
+		let terraced = grid.filter(Filter::Terrace { levels: 3 }, None);
+		let mut found = heights(&terraced);
+		found.sort_unstable();
+		found.dedup();
+		assert_eq!(found, vec![0, 15, 30]);
+
+		let quantized = grid.filter(Filter::Quantize { step: 4 }, None);
+		assert!(heights(&quantized).iter().all(|h| h % 4 == 0));
+		assert_eq!(quantized.0[0][2], Height(4));
+		assert_eq!(quantized.0[0][1], Height(0));
+
+		let normalized = grid.filter(Filter::Normalize { min: -10, max: 50 }, None);
+		assert_eq!(normalized.0[0][0], Height(-10));
+		assert_eq!(normalized.0[15][15], Height(50));
+		assert_eq!(normalized.0[0][15], Height(20));
+
+		let inverted = grid.filter(Filter::Invert, None);
+		assert_eq!(inverted.0[0][0], Height(30));
+		assert_eq!(inverted.filter(Filter::Invert, None), grid);
+	}
+
+	#[test]
+	fn masked() {
+		let grid = ramp();
+		let mut mask = [[false; 16]; 16];
+		mask[0][0] = true;
+		mask[0][1] = true;
+		let inverted = grid.filter(Filter::Invert, Some(&mask));
+		assert_eq!(inverted.0[0][0], Height(1));
+		assert_eq!(inverted.0[0][1], Height(0));
+		assert_eq!(inverted.0[0][2], Height(2));
+		assert_eq!(
+			grid.filter(Filter::Smooth { radius: 3 }, Some(&[[false; 16]; 16])),
+			grid
+		);
+		let clamped = grid.filter(Filter::Normalize { min: 0, max: 120 }, None);
+		assert_eq!(clamped.0[15][15], Height::MAX);
+	}
+}
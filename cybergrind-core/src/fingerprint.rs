@@ -0,0 +1,84 @@
+//! Hashes of patterns that stay the same across versions of this library
+//! and machines, for spotting copies of the same arena.
+
+use std::fmt;
+
+use crate::{Map, Symmetry};
+
+/// A 64-bit FNV-1a hash of a pattern, written as 16 hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fingerprint(pub u64);
+
+impl fmt::Display for Fingerprint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:016x}", self.0)
+	}
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+		(hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+	})
+}
+
+impl Map {
+	/// Every height as a byte, then every prefab as its character in pattern
+	/// files, in row order.
+	fn fingerprint_bytes(&self) -> Vec<u8> {
+		let heights = self.heights.iter().map(|(_, h)| h.0 as u8);
+		let prefabs = self.prefabs.iter().map(|(_, p)| p.to_char() as u8);
+		heights.chain(prefabs).collect()
+	}
+
+	pub fn fingerprint(&self) -> Fingerprint {
+		Fingerprint(fnv1a(&self.fingerprint_bytes()))
+	}
+
+	/// The one of this pattern's turned and mirrored copies that every other
+	/// one of them also picks, and the symmetry that makes it from `self`.
+	pub fn canonical(&self) -> (Map, Symmetry) {
+		Symmetry::ALL
+			.iter()
+			.map(|s| (self.apply(*s), *s))
+			.min_by_key(|(map, _)| map.fingerprint_bytes())
+			.unwrap()
+	}
+
+	/// A fingerprint that's the same for every turned and mirrored copy of
+	/// this pattern.
+	pub fn canonical_fingerprint(&self) -> Fingerprint {
+		self.canonical().0.fingerprint()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{test::lopsided, Parsable, Prefab};
+
+	#[test]
+	fn stable() {
+		assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+		assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+		assert_eq!(
+			Map::default().fingerprint().to_string(),
+			format!("{:016x}", fnv1a(&[[0; 256], [b'0'; 256]].concat()))
+		);
+		assert_ne!(lopsided().fingerprint(), Map::default().fingerprint());
+	}
+
+	#[test]
+	fn canonical() {
+		let map = lopsided();
+		let fingerprint = map.canonical_fingerprint();
+		for symmetry in Symmetry::ALL.iter() {
+			let turned = map.apply(*symmetry);
+			assert_eq!(turned.canonical_fingerprint(), fingerprint);
+			let (canonical, used) = turned.canonical();
+			assert_eq!(canonical.apply(used.inverse()), turned);
+		}
+		let mut changed = map;
+		changed.prefabs.0[3][1] = Prefab::Melee;
+		assert_ne!(changed.canonical_fingerprint(), fingerprint);
+	}
+}
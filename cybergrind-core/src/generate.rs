@@ -0,0 +1,367 @@
+//! Random arenas from a seed.
+//!
+//! Heights come from value noise, quantized into flat steps. The lowest
+//! parts of a second noise field become pits, and prefabs are scattered on
+//! what's left where they make sense. The same seed and options always make
+//! the same map.
+
+use crate::{
+	neighbours, rng::Rng, validate, Grid, Height, Map, Parsable, Prefab, Symmetry,
+};
+
+/// Which ways a generated map mirrors onto itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymmetryMode {
+	None,
+	/// The left half mirrored onto the right.
+	MirrorX,
+	/// The top half mirrored onto the bottom.
+	MirrorY,
+	/// One quarter mirrored onto the other three.
+	FourWay,
+	/// One half turned around onto the other.
+	Rotational,
+}
+
+impl SymmetryMode {
+	fn symmetries(self) -> &'static [Symmetry] {
+		use Symmetry::*;
+		match self {
+			SymmetryMode::None => &[Identity],
+			SymmetryMode::MirrorX => &[Identity, FlipHorizontal],
+			SymmetryMode::MirrorY => &[Identity, FlipVertical],
+			SymmetryMode::FourWay => {
+				&[Identity, FlipHorizontal, FlipVertical, Rotate180]
+			}
+			SymmetryMode::Rotational => &[Identity, Rotate180],
+		}
+	}
+
+	/// The tiles that have to match `(x, y)`, itself included.
+	fn copies(self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+		let mut copies: Vec<_> =
+			self.symmetries().iter().map(|s| s.source(x, y)).collect();
+		copies.sort_unstable();
+		copies.dedup();
+		copies
+	}
+
+	/// The one of a tile's copies that stands for all of them.
+	fn representative(self, tile: (usize, usize)) -> (usize, usize) {
+		self.copies(tile)[0]
+	}
+}
+
+/// How many of each prefab to scatter, before they're mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefabCounts {
+	pub melee: usize,
+	pub projectile: usize,
+	pub jump_pads: usize,
+	pub stairs: usize,
+	/// Hideous Masses aren't mirrored, there's never more than one.
+	pub hideous: bool,
+}
+
+impl Default for PrefabCounts {
+	fn default() -> Self {
+		Self {
+			melee: 3,
+			projectile: 3,
+			jump_pads: 1,
+			stairs: 2,
+			hideous: false,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerateOptions {
+	pub seed: u64,
+	pub symmetry: SymmetryMode,
+	/// Rough size in tiles of hills and valleys.
+	pub scale: f32,
+	/// Layers of noise, each finer than the last, for rougher terrain. Taken
+	/// to be between 1 and [`GenerateOptions::MAX_OCTAVES`].
+	pub octaves: u32,
+	pub min_height: i8,
+	pub max_height: i8,
+	/// Heights are rounded to multiples of this, making flat terraces.
+	pub step: i8,
+	/// Share of tiles, from 0 to 1, that are pits.
+	pub pit_density: f32,
+	pub prefabs: PrefabCounts,
+}
+
+impl GenerateOptions {
+	/// Past this, octaves are finer than a tile and change nothing.
+	pub const MAX_OCTAVES: u32 = 8;
+}
+
+impl Default for GenerateOptions {
+	fn default() -> Self {
+		Self {
+			seed: 0,
+			symmetry: SymmetryMode::MirrorX,
+			scale: 6.0,
+			octaves: 2,
+			min_height: 0,
+			max_height: 10,
+			step: 2,
+			pit_density: 0.05,
+			prefabs: PrefabCounts::default(),
+		}
+	}
+}
+
+/// Smooth noise in `0.0..1.0` from random values at whole coordinates.
+struct ValueNoise {
+	seed: u64,
+}
+
+impl ValueNoise {
+	fn lattice(&self, x: i64, y: i64) -> f32 {
+		let mixed = self.seed
+			^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+			^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+		Rng::new(mixed).next_f32()
+	}
+
+	fn at(&self, x: f32, y: f32) -> f32 {
+		let (x0, y0) = (x.floor(), y.floor());
+		let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+		let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+		let (x0, y0) = (x0 as i64, y0 as i64);
+		let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+		lerp(
+			lerp(self.lattice(x0, y0), self.lattice(x0 + 1, y0), tx),
+			lerp(self.lattice(x0, y0 + 1), self.lattice(x0 + 1, y0 + 1), tx),
+			ty,
+		)
+	}
+
+	/// Octaves of noise, each twice as fine and half as strong as the last.
+	fn fractal(&self, x: f32, y: f32, octaves: u32) -> f32 {
+		let (mut sum, mut weight, mut total) = (0.0, 1.0, 0.0);
+		for octave in 0..octaves.clamp(1, GenerateOptions::MAX_OCTAVES) {
+			let f = 2f32.powi(octave as i32);
+			sum += self.at(x * f, y * f) * weight;
+			total += weight;
+			weight /= 2.0;
+		}
+		sum / total
+	}
+}
+
+fn tiles() -> impl Iterator<Item = (usize, usize)> {
+	(0..16).flat_map(|y| (0..16).map(move |x| (x, y)))
+}
+
+/// Sets a tile and every copy of it.
+fn set<T: Parsable + Copy>(
+	grid: &mut Grid<T>,
+	mode: SymmetryMode,
+	tile: (usize, usize),
+	value: T,
+) {
+	for (x, y) in mode.copies(tile) {
+		grid.0[y][x] = value;
+	}
+}
+
+/// Whether `prefab` does its job on `tile`.
+fn fits(map: &Map, prefab: Prefab, (x, y): (usize, usize)) -> bool {
+	let height = map.heights.0[y][x];
+	let ground = |(x, y): (usize, usize)| !map.heights.0[y][x].is_pit();
+	match prefab {
+		Prefab::Stairs => neighbours((x, y)).any(|n| {
+			let other = map.heights.0[n.1][n.0];
+			ground(n) && other != height && (other.0 - height.0).abs() <= 4
+		}),
+		Prefab::JumpPad => neighbours((x, y)).any(ground),
+		_ => true,
+	}
+}
+
+/// Generates a map. It always parses back from its pattern file and passes
+/// [`validate`], though it may not all be reachable.
+pub fn generate(options: &GenerateOptions) -> Map {
+	let mode = options.symmetry;
+	let mut rng = Rng::new(options.seed);
+	let terrain = ValueNoise {
+		seed: rng.next_u64(),
+	};
+	let pits = ValueNoise {
+		seed: rng.next_u64(),
+	};
+	let representatives: Vec<_> =
+		tiles().filter(|t| mode.representative(*t) == *t).collect();
+
+	let mut map = Map {
+		heights: Grid::default(),
+		prefabs: Grid::default(),
+	};
+	let (low, high) = (
+		options.min_height.clamp(Height::PIT.0 + 1, Height::MAX.0),
+		options.max_height.clamp(Height::PIT.0 + 1, Height::MAX.0),
+	);
+	let (low, high) = (low.min(high) as f32, low.max(high) as f32);
+	let step = options.step.max(1) as f32;
+	let scale = options.scale.max(0.5);
+	for &(x, y) in &representatives {
+		let n =
+			terrain.fractal(x as f32 / scale, y as f32 / scale, options.octaves);
+		let h = low + n * (high - low + 1.0);
+		let h = ((h / step).floor() * step).clamp(low, high);
+		set(&mut map.heights, mode, (x, y), Height(h as i8));
+	}
+
+	let mut by_depth: Vec<_> = representatives
+		.iter()
+		.map(|&(x, y)| {
+			let n = pits.fractal(x as f32 / scale, y as f32 / scale, options.octaves);
+			(n, (x, y))
+		})
+		.collect();
+	by_depth.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+	let count =
+		(options.pit_density.clamp(0.0, 1.0) * by_depth.len() as f32) as usize;
+	for (_, tile) in &by_depth[..count] {
+		set(&mut map.heights, mode, *tile, Height::PIT);
+	}
+
+	let counts = &options.prefabs;
+	for (prefab, count) in [
+		(Prefab::Stairs, counts.stairs),
+		(Prefab::JumpPad, counts.jump_pads),
+		(Prefab::Melee, counts.melee),
+		(Prefab::Projectile, counts.projectile),
+	]
+	.iter()
+	{
+		let mut spots: Vec<_> = representatives
+			.iter()
+			.copied()
+			.filter(|&(x, y)| {
+				!map.heights.0[y][x].is_pit()
+					&& map.prefabs.0[y][x] == Prefab::None
+					&& fits(&map, *prefab, (x, y))
+			})
+			.collect();
+		rng.shuffle(&mut spots);
+		for tile in spots.into_iter().take(*count) {
+			set(&mut map.prefabs, mode, tile, *prefab);
+		}
+	}
+
+	// Copies of a prefab can end up next to each other across the middle,
+	// like jump pads with only each other to land on. Those get taken out.
+	loop {
+		let tiles: Vec<_> = validate(&map)
+			.into_iter()
+			.flat_map(|d| d.tiles)
+			.filter(|&(x, y)| map.prefabs.0[y][x] != Prefab::None)
+			.collect();
+		if tiles.is_empty() {
+			break;
+		}
+		for tile in tiles {
+			set(&mut map.prefabs, mode, tile, Prefab::None);
+		}
+	}
+
+	if counts.hideous {
+		let spots: Vec<_> = tiles()
+			.filter(|&(x, y)| {
+				!map.heights.0[y][x].is_pit() && map.prefabs.0[y][x] == Prefab::None
+			})
+			.collect();
+		if !spots.is_empty() {
+			let (x, y) = spots[rng.below(spots.len())];
+			map.prefabs.0[y][x] = Prefab::Hideous;
+		}
+	}
+	map
+}
+
+impl Map {
+	pub fn generate(options: &GenerateOptions) -> Self {
+		generate(options)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::str::FromStr;
+
+	use super::*;
+
+	#[test]
+	fn seeded() {
+		let options = GenerateOptions::default();
+		assert_eq!(generate(&options), generate(&options));
+		assert_ne!(
+			generate(&options),
+			generate(&GenerateOptions { seed: 1, ..options })
+		);
+	}
+
+	#[test]
+	fn octave_limit() {
+		let options = |octaves| GenerateOptions {
+			octaves,
+			..Default::default()
+		};
+		assert_eq!(
+			generate(&options(u32::MAX)),
+			generate(&options(GenerateOptions::MAX_OCTAVES))
+		);
+		assert_eq!(generate(&options(0)), generate(&options(1)));
+	}
+
+	#[test]
+	fn valid() {
+		for seed in 0..40 {
+			let options = GenerateOptions {
+				seed,
+				pit_density: 0.2,
+				prefabs: PrefabCounts {
+					hideous: true,
+					..PrefabCounts::default()
+				},
+				..GenerateOptions::default()
+			};
+			let map = generate(&options);
+			assert_eq!(Map::from_str(&map.to_string()), Ok(map));
+			assert_eq!(validate(&map), vec![], "seed {}", seed);
+			let pits = map.heights.iter().filter(|(_, h)| h.is_pit()).count();
+			assert!((pits as f32 / 256.0 - 0.2).abs() < 0.02, "{} pits", pits);
+			assert!(map.heights.iter().all(|(_, h)| h.is_pit() || h.0 % 2 == 0));
+		}
+	}
+
+	#[test]
+	fn symmetric() {
+		for (mode, symmetries) in [
+			(SymmetryMode::MirrorX, &[Symmetry::FlipHorizontal][..]),
+			(SymmetryMode::MirrorY, &[Symmetry::FlipVertical]),
+			(
+				SymmetryMode::FourWay,
+				&[Symmetry::FlipHorizontal, Symmetry::FlipVertical],
+			),
+			(SymmetryMode::Rotational, &[Symmetry::Rotate180]),
+		]
+		.iter()
+		{
+			let map = generate(&GenerateOptions {
+				seed: 3,
+				symmetry: *mode,
+				..GenerateOptions::default()
+			});
+			for symmetry in symmetries.iter() {
+				assert_eq!(map.apply(*symmetry), map, "{:?}", mode);
+			}
+			assert_ne!(map.apply(Symmetry::Transpose), map);
+		}
+	}
+}
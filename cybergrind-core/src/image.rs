@@ -0,0 +1,351 @@
+//! Pattern layers as PNG images, so arenas can be sketched in an image
+//! editor.
+//!
+//! Images are square with a side that's a multiple of 16, each tile covering
+//! a block of pixels. When reading, the pixel in the middle of each block is
+//! the one that counts.
+
+use std::{
+	fmt,
+	io::{Read, Write},
+};
+
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use crate::{Grid, Height, Parsable, Prefab, RenderOptions};
+
+#[derive(Debug)]
+pub enum ImageError {
+	Decoding(png::DecodingError),
+	Encoding(png::EncodingError),
+	/// The image isn't a square with a side that's a multiple of 16.
+	Size {
+		width: u32,
+		height: u32,
+	},
+}
+
+impl fmt::Display for ImageError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ImageError::Decoding(e) => write!(f, "couldn't read image: {}", e),
+			ImageError::Encoding(e) => write!(f, "couldn't write image: {}", e),
+			ImageError::Size { width, height } => write!(
+				f,
+				"image is {}x{}, expected a square with a side that's a multiple of 16",
+				width, height
+			),
+		}
+	}
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<png::DecodingError> for ImageError {
+	fn from(e: png::DecodingError) -> Self {
+		ImageError::Decoding(e)
+	}
+}
+
+impl From<png::EncodingError> for ImageError {
+	fn from(e: png::EncodingError) -> Self {
+		ImageError::Encoding(e)
+	}
+}
+
+/// How heights are drawn as shades of gray. `black` and anything below it is
+/// black, `white` and anything above it is white, and the heights in between
+/// are spread evenly. Swap them to draw higher tiles darker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrayScale {
+	pub black: Height,
+	pub white: Height,
+}
+
+/// Covers every valid height, so any pattern survives a round trip.
+impl Default for GrayScale {
+	fn default() -> Self {
+		Self {
+			black: Height::MIN,
+			white: Height::MAX,
+		}
+	}
+}
+
+impl GrayScale {
+	fn span(&self) -> f32 {
+		self.white.0 as f32 - self.black.0 as f32
+	}
+
+	pub fn to_gray(&self, height: Height) -> u8 {
+		if self.black == self.white {
+			return if height.0 > self.black.0 { 255 } else { 0 };
+		}
+		let t = (height.0 as f32 - self.black.0 as f32) / self.span();
+		(t * 255.0).round().clamp(0.0, 255.0) as u8
+	}
+
+	pub fn to_height(&self, gray: u8) -> Height {
+		let h = self.black.0 as f32 + gray as f32 / 255.0 * self.span();
+		Height(h.round() as i8)
+	}
+}
+
+/// The color a prefab is drawn with, see [`Prefab::color`]. Empty tiles are
+/// transparent.
+pub fn prefab_color(prefab: Prefab) -> [u8; 4] {
+	let [r, g, b] = prefab.color();
+	let alpha = if prefab == Prefab::None { 0 } else { 255 };
+	[r, g, b, alpha]
+}
+
+/// The prefab whose color is closest to `rgba`. Mostly transparent pixels
+/// are empty tiles, and so are unknown prefabs, since their color doesn't
+/// say which character they were.
+fn nearest_prefab(rgba: [u8; 4]) -> Prefab {
+	if rgba[3] < 128 {
+		return Prefab::None;
+	}
+	let distance = |color: [u8; 3]| {
+		(0..3)
+			.map(|i| (color[i] as i32 - rgba[i] as i32).pow(2))
+			.sum::<i32>()
+	};
+	let nearest = *Prefab::ALL
+		.iter()
+		.min_by_key(|p| distance(p.color()))
+		.unwrap();
+	if distance(Prefab::UNKNOWN_COLOR) < distance(nearest.color()) {
+		Prefab::None
+	} else {
+		nearest
+	}
+}
+
+/// Writes a grid as an image with `tile_size` pixels per side of each tile,
+/// `pixel` giving the color channels of a tile. The tile size is capped the
+/// same way as for rendering.
+fn encode<T: Parsable + Copy, W: Write>(
+	grid: &Grid<T>,
+	tile_size: u32,
+	color: ColorType,
+	pixel: impl Fn(T) -> Vec<u8>,
+	w: W,
+) -> Result<(), ImageError> {
+	let tile_size = tile_size.clamp(1, RenderOptions::MAX_TILE_SIZE) as usize;
+	let side = 16 * tile_size;
+	let mut data = Vec::new();
+	for tiles in grid.0.iter() {
+		let mut line = Vec::new();
+		for tile in tiles.iter() {
+			let channels = pixel(*tile);
+			for _ in 0..tile_size {
+				line.extend_from_slice(&channels);
+			}
+		}
+		for _ in 0..tile_size {
+			data.extend_from_slice(&line);
+		}
+	}
+	let mut encoder = Encoder::new(w, side as u32, side as u32);
+	encoder.set_color(color);
+	encoder.set_depth(BitDepth::Eight);
+	encoder.write_header()?.write_image_data(&data)?;
+	Ok(())
+}
+
+/// Reads the middle pixel of each tile of an image as RGBA.
+fn decode<R: Read>(r: R) -> Result<[[[u8; 4]; 16]; 16], ImageError> {
+	let mut decoder = Decoder::new(r);
+	decoder
+		.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+	let (info, mut reader) = decoder.read_info()?;
+	let (width, height) = (info.width, info.height);
+	if width != height || width == 0 || width % 16 != 0 {
+		return Err(ImageError::Size { width, height });
+	}
+	let mut buf = vec![0; info.buffer_size()];
+	reader.next_frame(&mut buf)?;
+
+	let channels = info.color_type.samples();
+	let tile_size = width as usize / 16;
+	let mut pixels = [[[0; 4]; 16]; 16];
+	for (y, row) in pixels.iter_mut().enumerate() {
+		for (x, pixel) in row.iter_mut().enumerate() {
+			let px = x * tile_size + tile_size / 2;
+			let py = y * tile_size + tile_size / 2;
+			let i = py * info.line_size + px * channels;
+			let p = &buf[i..i + channels];
+			*pixel = match info.color_type {
+				ColorType::Grayscale => [p[0], p[0], p[0], 255],
+				ColorType::GrayscaleAlpha => [p[0], p[0], p[0], p[1]],
+				ColorType::RGB => [p[0], p[1], p[2], 255],
+				_ => [p[0], p[1], p[2], p[3]],
+			};
+		}
+	}
+	Ok(pixels)
+}
+
+fn luma([r, g, b, _]: [u8; 4]) -> u8 {
+	((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+/// Writes heights as a grayscale PNG, each tile `tile_size` pixels across.
+/// A `tile_size` of 0 is treated as 1, and sizes above
+/// [`RenderOptions::MAX_TILE_SIZE`] as that.
+pub fn write_heights<W: Write>(
+	heights: &Grid<Height>,
+	scale: &GrayScale,
+	tile_size: u32,
+	w: W,
+) -> Result<(), ImageError> {
+	encode(
+		heights,
+		tile_size,
+		ColorType::Grayscale,
+		|h| vec![scale.to_gray(h)],
+		w,
+	)
+}
+
+/// Reads heights from a PNG in any color type, colors being read by their
+/// brightness.
+pub fn read_heights<R: Read>(
+	r: R,
+	scale: &GrayScale,
+) -> Result<Grid<Height>, ImageError> {
+	let pixels = decode(r)?;
+	let mut heights = Grid::<Height>::default();
+	for (row, pixels) in heights.0.iter_mut().zip(pixels.iter()) {
+		for (height, pixel) in row.iter_mut().zip(pixels.iter()) {
+			*height = scale.to_height(luma(*pixel));
+		}
+	}
+	Ok(heights)
+}
+
+/// Writes prefabs as a PNG colored by [`prefab_color`], each tile
+/// `tile_size` pixels across. A `tile_size` of 0 is treated as 1, and sizes
+/// above [`RenderOptions::MAX_TILE_SIZE`] as that.
+pub fn write_prefabs<W: Write>(
+	prefabs: &Grid<Prefab>,
+	tile_size: u32,
+	w: W,
+) -> Result<(), ImageError> {
+	encode(
+		prefabs,
+		tile_size,
+		ColorType::RGBA,
+		|p| prefab_color(p).to_vec(),
+		w,
+	)
+}
+
+/// Reads prefabs from a PNG, taking whichever prefab's color is closest to
+/// each tile's.
+pub fn read_prefabs<R: Read>(r: R) -> Result<Grid<Prefab>, ImageError> {
+	let pixels = decode(r)?;
+	let mut prefabs = Grid::<Prefab>::default();
+	for (row, pixels) in prefabs.0.iter_mut().zip(pixels.iter()) {
+		for (prefab, pixel) in row.iter_mut().zip(pixels.iter()) {
+			*prefab = nearest_prefab(*pixel);
+		}
+	}
+	Ok(prefabs)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Map, UnknownChar};
+
+	fn sample() -> Map {
+		let mut map = Map::default();
+		map.heights.0[0][1] = Height(-15);
+		map.heights.0[3][2] = Height(7);
+		map.heights.0[15][15] = Height::MAX;
+		map.prefabs.0[3][2] = Prefab::JumpPad;
+		map.prefabs.0[9][4] = Prefab::Hideous;
+		map
+	}
+
+	#[test]
+	fn gray_scale() {
+		let scale = GrayScale::default();
+		assert_eq!(scale.to_gray(Height::MIN), 0);
+		assert_eq!(scale.to_gray(Height::MAX), 255);
+		for h in Height::MIN.0..=Height::MAX.0 {
+			assert_eq!(scale.to_height(scale.to_gray(Height(h))), Height(h));
+		}
+
+		let inverted = GrayScale {
+			black: Height(10),
+			white: Height(0),
+		};
+		assert_eq!(inverted.to_gray(Height(0)), 255);
+		assert_eq!(inverted.to_gray(Height(-15)), 255);
+		assert_eq!(inverted.to_gray(Height(20)), 0);
+		assert_eq!(inverted.to_height(0), Height(10));
+	}
+
+	#[test]
+	fn heights_round_trip() {
+		let map = sample();
+		for tile_size in [1, 4].iter() {
+			let mut png = Vec::new();
+			write_heights(&map.heights, &GrayScale::default(), *tile_size, &mut png)
+				.unwrap();
+			let read = read_heights(&png[..], &GrayScale::default()).unwrap();
+			assert_eq!(read, map.heights);
+		}
+	}
+
+	#[test]
+	fn prefabs_round_trip() {
+		let map = sample();
+		let mut png = Vec::new();
+		write_prefabs(&map.prefabs, 3, &mut png).unwrap();
+		assert_eq!(read_prefabs(&png[..]).unwrap(), map.prefabs);
+	}
+
+	#[test]
+	fn tile_size_limit() {
+		let mut png = Vec::new();
+		write_heights(&sample().heights, &GrayScale::default(), u32::MAX, &mut png)
+			.unwrap();
+		let (info, _) = Decoder::new(&png[..]).read_info().unwrap();
+		assert_eq!(info.width, 16 * RenderOptions::MAX_TILE_SIZE);
+	}
+
+	#[test]
+	fn nearest_color() {
+		assert_eq!(nearest_prefab([250, 10, 10, 255]), Prefab::Melee);
+		assert_eq!(nearest_prefab([250, 10, 10, 0]), Prefab::None);
+		assert_eq!(
+			nearest_prefab(prefab_color(Prefab::Unknown(UnknownChar('x')))),
+			Prefab::None
+		);
+		assert_eq!(nearest_prefab([10, 10, 10, 255]), Prefab::None);
+	}
+
+	#[test]
+	fn bad_size() {
+		let mut png = Vec::new();
+		let mut encoder = Encoder::new(&mut png, 16, 20);
+		encoder.set_color(ColorType::Grayscale);
+		encoder.set_depth(BitDepth::Eight);
+		encoder
+			.write_header()
+			.unwrap()
+			.write_image_data(&[0; 16 * 20])
+			.unwrap();
+		assert!(matches!(
+			read_heights(&png[..], &GrayScale::default()),
+			Err(ImageError::Size {
+				width: 16,
+				height: 20
+			})
+		));
+	}
+}
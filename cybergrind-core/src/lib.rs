@@ -1,39 +1,166 @@
+use std::{convert::TryFrom, fmt, str::FromStr};
+
 use nom::{
 	branch::alt,
-	character::complete::{char, digit1, line_ending, one_of},
-	combinator::{map, opt, recognize},
-	multi::{many1, separated_list1},
-	sequence::{delimited, pair, separated_pair},
+	character::complete::{anychar, char, digit1, line_ending, one_of},
+	combinator::{cut, map, map_opt, map_res, opt, recognize},
+	sequence::{pair, preceded, separated_pair, terminated},
+};
+
+pub use ansi::{render_ansi, AnsiOptions};
+pub use csv::{CsvCell, CsvError};
+pub use diff::{MapDiff, TileChange};
+#[cfg(feature = "metadata")]
+pub use document::{DocumentError, Metadata, PatternDocument};
+pub use filter::{Filter, Mask};
+pub use fingerprint::Fingerprint;
+pub use generate::{generate, GenerateOptions, PrefabCounts, SymmetryMode};
+#[cfg(feature = "png")]
+pub use image::{GrayScale, ImageError};
+pub use merge::{merge, Conflict, Conflicts, Merge};
+#[cfg(feature = "metadata")]
+pub use pack::{Pack, PackEntry, PackError};
+pub use parse::{
+	Expected, ParseError, ParseErrorKind, ParseOptions, Section, TokenError,
+	TokenResult,
 };
+#[cfg(feature = "png")]
+pub use render::{render, Canvas, RenderOptions, View};
+pub use share::ShareCodeError;
+pub use similarity::{rank, Distance, DistanceOptions};
+pub use svg::{render_svg, SvgOptions};
+pub use transform::Symmetry;
+pub use validate::{validate, Diagnostic, Rule, Severity, Validator};
+pub use walk::{Move, Movement, Walkability};
+pub use wfc::{Wfc, WfcError, WfcOptions};
+
+use parse::expect;
+
+pub mod ansi;
+pub mod csv;
+mod diff;
+#[cfg(feature = "metadata")]
+pub mod document;
+pub mod filter;
+mod fingerprint;
+pub mod generate;
+#[cfg(feature = "png")]
+pub mod image;
+pub mod merge;
+pub mod mesh;
+#[cfg(feature = "metadata")]
+pub mod pack;
+mod palette;
+mod parse;
+#[cfg(feature = "png")]
+pub mod render;
+mod rng;
+pub mod share;
+mod similarity;
+pub mod svg;
+mod transform;
+pub mod validate;
+pub mod walk;
+pub mod wfc;
 
 pub trait Parsable: Sized + ToString {
-	fn parse(input: &str) -> nom::IResult<&str, Self>;
+	fn parse(input: &str) -> TokenResult<'_, Self>;
 
 	fn default() -> Self;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Map {
 	pub heights: Grid<Height>,
 	pub prefabs: Grid<Prefab>,
 }
 
 impl Map {
-	pub fn from_str(
+	/// Parses a pattern file. `Map::from_str` is this with
+	/// [`ParseOptions::lenient`].
+	pub fn from_str_with(
 		input: &str,
-	) -> Result<Self, nom::Err<nom::error::Error<&str>>> {
-		Self::parse(input).map(|v| v.1)
+		options: ParseOptions,
+	) -> Result<Self, ParseError> {
+		let mut body = input;
+		if let Some(rest) = body.strip_prefix('\u{feff}') {
+			if !options.allow_bom {
+				return Err(ParseError::at(
+					input,
+					body,
+					Section::Heights,
+					ParseErrorKind::ByteOrderMark,
+				));
+			}
+			body = rest;
+		}
+		if options.allow_surrounding_whitespace {
+			body = body.trim();
+		}
+		if !options.allow_crlf {
+			if let Some(cr) = body.find('\r') {
+				// the 16 height rows and the blank line after them
+				let section = if body[..cr].matches('\n').count() < 17 {
+					Section::Heights
+				} else {
+					Section::Prefabs
+				};
+				return Err(ParseError::at(
+					input,
+					&body[cr..],
+					section,
+					ParseErrorKind::CarriageReturn,
+				));
+			}
+		}
+
+		let (rest, heights) = Grid::<Height>::parse(body)
+			.map_err(|e| ParseError::from_nom(input, Section::Heights, e))?;
+		let (rest, _) = blank_line(rest)
+			.map_err(|e| ParseError::from_nom(input, Section::Heights, e))?;
+		let (rest, prefabs) = Grid::<Prefab>::parse(rest)
+			.map_err(|e| ParseError::from_nom(input, Section::Prefabs, e))?;
+		if !rest.is_empty() && !options.allow_trailing_content {
+			return Err(ParseError::at(
+				input,
+				rest,
+				Section::Prefabs,
+				ParseErrorKind::TrailingContent,
+			));
+		}
+		Ok(Self { heights, prefabs })
+	}
+
+	/// Like [`Map::from_str`], but carries on past malformed tiles, rows and
+	/// separators and returns every problem found alongside a best-effort map.
+	pub fn from_str_recovering(input: &str) -> (Self, Vec<ParseError>) {
+		parse::recover_map(input)
+	}
+}
+
+fn blank_line(input: &str) -> TokenResult<'_, ()> {
+	map(
+		pair(
+			expect(Expected::LineEnding, line_ending),
+			expect(Expected::BlankLine, line_ending),
+		),
+		|_| (),
+	)(input)
+}
+
+impl FromStr for Map {
+	type Err = ParseError;
+
+	fn from_str(input: &str) -> Result<Self, ParseError> {
+		Self::from_str_with(input, ParseOptions::default())
 	}
 }
 
 impl Parsable for Map {
-	fn parse(input: &str) -> nom::IResult<&str, Self> {
+	fn parse(input: &str) -> TokenResult<'_, Self> {
 		map(
-			separated_pair(
-				Grid::<Height>::parse,
-				pair(line_ending, line_ending),
-				Grid::<Prefab>::parse,
-			),
+			separated_pair(Grid::<Height>::parse, blank_line, Grid::<Prefab>::parse),
 			|(heights, prefabs)| Self { heights, prefabs },
 		)(input)
 	}
@@ -46,17 +173,14 @@ impl Parsable for Map {
 	}
 }
 
-impl ToString for Map {
-	fn to_string(&self) -> String {
-		format!(
-			"{}\n\n{}",
-			self.heights.to_string(),
-			self.prefabs.to_string()
-		)
+impl fmt::Display for Map {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}\n\n{}", self.heights, self.prefabs)
 	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Grid<T: Parsable>(pub [[T; 16]; 16]);
 
 impl<T: Parsable> Grid<T> {
@@ -74,21 +198,40 @@ impl<T: Parsable> Grid<T> {
 			Some(&mut self.0[y][x])
 		}
 	}
+	/// Every tile along with its `(x, y)` position, row by row.
+	pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+		self.0.iter().enumerate().flat_map(|(y, row)| {
+			row.iter().enumerate().map(move |(x, item)| ((x, y), item))
+		})
+	}
 }
 
-impl<T: Parsable + Copy> Parsable for Grid<T> {
-	fn parse(input: &str) -> nom::IResult<&str, Self> {
-		fn to_array<T, const N: usize>(v: Vec<T>) -> [T; N] {
-			use std::convert::TryInto;
+/// The up to four tiles orthogonally adjacent to `(x, y)`.
+pub(crate) fn neighbours(
+	(x, y): (usize, usize),
+) -> impl Iterator<Item = (usize, usize)> {
+	let (x, y) = (x as isize, y as isize);
+	vec![(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)]
+		.into_iter()
+		.filter(|(x, y)| (0..16).contains(x) && (0..16).contains(y))
+		.map(|(x, y)| (x as usize, y as usize))
+}
 
-			v.try_into().unwrap_or_else(|v: Vec<T>| {
-				panic!("Expected a Vec of length {} but got length {}", N, v.len())
-			})
+impl<T: Parsable + Copy> Parsable for Grid<T> {
+	fn parse(input: &str) -> TokenResult<'_, Self> {
+		let mut grid = Self::default();
+		let mut rest = input;
+		for (y, row) in grid.0.iter_mut().enumerate() {
+			if y > 0 {
+				rest = expect(Expected::LineEnding, line_ending)(rest)?.0;
+			}
+			for item in row.iter_mut() {
+				let (r, v) = T::parse(rest)?;
+				*item = v;
+				rest = r;
+			}
 		}
-
-		map(separated_list1(line_ending, many1(T::parse)), |vals| {
-			Self(to_array(vals.into_iter().map(|v| to_array(v)).collect()))
-		})(input)
+		Ok((rest, grid))
 	}
 
 	fn default() -> Self {
@@ -96,32 +239,132 @@ impl<T: Parsable + Copy> Parsable for Grid<T> {
 	}
 }
 
-impl<T: Parsable> ToString for Grid<T> {
-	fn to_string(&self) -> String {
-		let mut s = String::new();
+impl<T: Parsable> fmt::Display for Grid<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		for (i, row) in self.0.iter().enumerate() {
 			for item in row {
-				s.push_str(&item.to_string());
+				f.write_str(&item.to_string())?;
 			}
 			if i < self.0.len() - 1 {
-				s.push('\n');
+				writeln!(f)?;
 			}
 		}
-		s
+		Ok(())
+	}
+}
+
+/// The height of a tile, always within [`Height::MIN`]..=[`Height::MAX`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(try_from = "i8", into = "i8")
+)]
+pub struct Height(i8);
+
+impl Height {
+	/// Lowest height the game accepts.
+	pub const MIN: Self = Self(-50);
+	/// Highest height the game accepts.
+	pub const MAX: Self = Self(50);
+	/// Tiles at or below this height are pits the player falls into.
+	pub const PIT: Self = Self(-15);
+
+	/// `None` if `height` is out of range.
+	pub const fn new(height: i8) -> Option<Self> {
+		if height < Self::MIN.0 || height > Self::MAX.0 {
+			None
+		} else {
+			Some(Self(height))
+		}
+	}
+
+	/// The closest height to `height` that's in range.
+	pub fn clamped(height: i32) -> Self {
+		Self(height.clamp(Self::MIN.0 as i32, Self::MAX.0 as i32) as i8)
+	}
+
+	pub const fn get(self) -> i8 {
+		self.0
+	}
+
+	pub fn checked_add(self, offset: i8) -> Option<Self> {
+		self.0.checked_add(offset).and_then(Self::new)
+	}
+
+	pub fn checked_sub(self, offset: i8) -> Option<Self> {
+		self.0.checked_sub(offset).and_then(Self::new)
+	}
+
+	/// Adds `offset`, stopping at [`Height::MIN`] or [`Height::MAX`].
+	pub fn saturating_add(self, offset: i8) -> Self {
+		Self::clamped(self.0 as i32 + offset as i32)
+	}
+
+	/// Subtracts `offset`, stopping at [`Height::MIN`] or [`Height::MAX`].
+	pub fn saturating_sub(self, offset: i8) -> Self {
+		Self::clamped(self.0 as i32 - offset as i32)
+	}
+
+	pub fn is_pit(self) -> bool {
+		self.0 <= Self::PIT.0
 	}
 }
 
+/// A number outside of [`Height::MIN`]..=[`Height::MAX`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Height(pub i8);
+pub struct InvalidHeight(pub i8);
+
+impl fmt::Display for InvalidHeight {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"height {} is outside of {}..={}",
+			self.0,
+			Height::MIN.0,
+			Height::MAX.0
+		)
+	}
+}
+
+impl std::error::Error for InvalidHeight {}
+
+impl TryFrom<i8> for Height {
+	type Error = InvalidHeight;
+
+	fn try_from(height: i8) -> Result<Self, InvalidHeight> {
+		Self::new(height).ok_or(InvalidHeight(height))
+	}
+}
+
+impl From<Height> for i8 {
+	fn from(height: Height) -> Self {
+		height.0
+	}
+}
 
 impl Parsable for Height {
-	fn parse(input: &str) -> nom::IResult<&str, Self> {
-		let single = recognize(one_of("0123456789"));
-		let paren = recognize(pair(opt(char('-')), digit1));
-		map(
-			alt((single, delimited(char('('), paren, char(')')))),
-			|s: &str| Self(s.parse::<i8>().expect("Could not parse height")),
-		)(input)
+	fn parse(input: &str) -> TokenResult<'_, Self> {
+		let single =
+			map(
+				one_of("0123456789"),
+				|c| Self(c.to_digit(10).unwrap() as i8),
+			);
+		let number = map_res(
+			map_res(
+				expect(
+					Expected::HeightNumber,
+					recognize(pair(opt(char('-')), digit1)),
+				),
+				|s: &str| s.parse::<i8>(),
+			),
+			Self::try_from,
+		);
+		let paren = preceded(
+			char('('),
+			cut(terminated(number, expect(Expected::CloseParen, char(')')))),
+		);
+		expect(Expected::Height, alt((single, paren)))(input)
 	}
 
 	fn default() -> Self {
@@ -129,17 +372,27 @@ impl Parsable for Height {
 	}
 }
 
-impl ToString for Height {
-	fn to_string(&self) -> String {
+impl fmt::Display for Height {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		if self.0 <= 9 && self.0 >= 0 {
-			format!("{}", self.0)
+			write!(f, "{}", self.0)
 		} else {
-			format!("({})", self.0)
+			write!(f, "({})", self.0)
 		}
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The editor's prefab icons as a PNG, 16x16 each, stacked top to bottom:
+/// Hideous, Stairs, JumpPad, Projectile, Melee and unknown prefabs.
+pub const PREFAB_ATLAS: &[u8] = include_bytes!("../assets/prefabs.png");
+
+/// Serialized as its character in pattern files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(try_from = "char", into = "char")
+)]
 pub enum Prefab {
 	None,
 	Melee,
@@ -147,19 +400,149 @@ pub enum Prefab {
 	JumpPad,
 	Stairs,
 	Hideous,
+	/// A letter or digit this library doesn't know, kept as it is so that
+	/// patterns from newer versions of the game still open.
+	Unknown(UnknownChar),
+}
+
+/// A letter or digit that isn't the character of any known prefab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(try_from = "char", into = "char")
+)]
+pub struct UnknownChar(char);
+
+impl UnknownChar {
+	/// `None` if `c` isn't an ASCII letter or digit, or is a known prefab.
+	pub fn new(c: char) -> Option<Self> {
+		match Prefab::from_char(c) {
+			Some(Prefab::Unknown(unknown)) => Some(unknown),
+			_ => None,
+		}
+	}
+
+	pub fn get(self) -> char {
+		self.0
+	}
+}
+
+/// A character that [`UnknownChar`] can't hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidUnknownChar(pub char);
+
+impl fmt::Display for InvalidUnknownChar {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?} is not an unknown prefab character", self.0)
+	}
+}
+
+impl std::error::Error for InvalidUnknownChar {}
+
+impl TryFrom<char> for UnknownChar {
+	type Error = InvalidUnknownChar;
+
+	fn try_from(c: char) -> Result<Self, InvalidUnknownChar> {
+		Self::new(c).ok_or(InvalidUnknownChar(c))
+	}
+}
+
+impl From<UnknownChar> for char {
+	fn from(c: UnknownChar) -> Self {
+		c.0
+	}
+}
+
+/// A character that isn't any [`Prefab`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidPrefab(pub char);
+
+impl fmt::Display for InvalidPrefab {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?} is not a prefab character", self.0)
+	}
+}
+
+impl std::error::Error for InvalidPrefab {}
+
+impl TryFrom<char> for Prefab {
+	type Error = InvalidPrefab;
+
+	fn try_from(c: char) -> Result<Self, InvalidPrefab> {
+		Self::from_char(c).ok_or(InvalidPrefab(c))
+	}
+}
+
+impl From<Prefab> for char {
+	fn from(prefab: Prefab) -> Self {
+		prefab.to_char()
+	}
+}
+
+impl Prefab {
+	/// Every known prefab, starting with empty tiles.
+	pub const ALL: [Self; 6] = [
+		Prefab::None,
+		Prefab::Melee,
+		Prefab::Projectile,
+		Prefab::JumpPad,
+		Prefab::Stairs,
+		Prefab::Hideous,
+	];
+
+	/// The prefab written as `c` in pattern files, `None` if `c` can't be
+	/// one.
+	pub fn from_char(c: char) -> Option<Self> {
+		match c {
+			'0' => Some(Prefab::None),
+			'n' => Some(Prefab::Melee),
+			'p' => Some(Prefab::Projectile),
+			'J' => Some(Prefab::JumpPad),
+			's' => Some(Prefab::Stairs),
+			'H' => Some(Prefab::Hideous),
+			c if c.is_ascii_alphanumeric() => Some(Prefab::Unknown(UnknownChar(c))),
+			_ => None,
+		}
+	}
+
+	/// The character a prefab is written as in pattern files.
+	pub fn to_char(self) -> char {
+		match self {
+			Prefab::None => '0',
+			Prefab::Melee => 'n',
+			Prefab::Projectile => 'p',
+			Prefab::JumpPad => 'J',
+			Prefab::Stairs => 's',
+			Prefab::Hideous => 'H',
+			Prefab::Unknown(c) => c.0,
+		}
+	}
+
+	pub fn is_unknown(self) -> bool {
+		matches!(self, Prefab::Unknown(_))
+	}
+
+	/// The color every unknown prefab is drawn with.
+	pub const UNKNOWN_COLOR: [u8; 3] = [140, 140, 140];
+
+	/// The color a prefab is drawn with in images and exported models.
+	pub fn color(self) -> [u8; 3] {
+		match self {
+			Prefab::None => [0, 0, 0],
+			Prefab::Melee => [220, 40, 40],
+			Prefab::Projectile => [240, 200, 40],
+			Prefab::JumpPad => [60, 200, 60],
+			Prefab::Stairs => [60, 100, 230],
+			Prefab::Hideous => [170, 60, 200],
+			Prefab::Unknown(_) => Self::UNKNOWN_COLOR,
+		}
+	}
 }
 
 impl Parsable for Prefab {
-	fn parse(input: &str) -> nom::IResult<&str, Self> {
-		map(one_of("0npJsH"), |c| match c {
-			'0' => Prefab::None,
-			'n' => Prefab::Melee,
-			'p' => Prefab::Projectile,
-			'J' => Prefab::JumpPad,
-			's' => Prefab::Stairs,
-			'H' => Prefab::Hideous,
-			_ => panic!("Invalid prefab character"),
-		})(input)
+	fn parse(input: &str) -> TokenResult<'_, Self> {
+		expect(Expected::Prefab, map_opt(anychar, Prefab::from_char))(input)
 	}
 
 	fn default() -> Self {
@@ -167,22 +550,14 @@ impl Parsable for Prefab {
 	}
 }
 
-impl ToString for Prefab {
-	fn to_string(&self) -> String {
-		match self {
-			Prefab::None => "0",
-			Prefab::Melee => "n",
-			Prefab::Projectile => "p",
-			Prefab::JumpPad => "J",
-			Prefab::Stairs => "s",
-			Prefab::Hideous => "H",
-		}
-		.to_string()
+impl fmt::Display for Prefab {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.to_char())
 	}
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
 	use super::*;
 
 	#[test]
@@ -194,9 +569,56 @@ mod test {
 		assert_eq!(Height::parse("55").unwrap().1, Height(5));
 	}
 
+	#[test]
+	fn parse_height_out_of_range() {
+		let err = Height::parse("(300)").unwrap_err();
+		match err {
+			nom::Err::Failure(e) => {
+				assert_eq!(e.kind, ParseErrorKind::HeightOutOfRange("300".to_string()))
+			}
+			_ => panic!("expected a failure, got {:?}", err),
+		}
+	}
+
+	#[test]
+	fn parse_height_outside_game_range() {
+		assert_eq!(Height::parse("(-50)").unwrap().1, Height::MIN);
+		assert_eq!(Height::parse("(50)").unwrap().1, Height::MAX);
+		for input in ["(51)", "(-51)", "(127)"].iter() {
+			match Height::parse(input).unwrap_err() {
+				nom::Err::Failure(e) => assert_eq!(
+					e.kind,
+					ParseErrorKind::HeightOutOfRange(input[1..input.len() - 1].into())
+				),
+				err => panic!("expected a failure, got {:?}", err),
+			}
+		}
+	}
+
+	#[test]
+	fn height_arithmetic() {
+		assert_eq!(Height::new(-50), Some(Height::MIN));
+		assert_eq!(Height::new(51), None);
+		assert_eq!(Height::try_from(-51), Err(InvalidHeight(-51)));
+		assert_eq!(Height::clamped(1000), Height::MAX);
+		assert_eq!(Height::clamped(-3).get(), -3);
+
+		let h = Height::new(45).unwrap();
+		assert_eq!(h.checked_add(5), Some(Height::MAX));
+		assert_eq!(h.checked_add(6), None);
+		assert_eq!(h.checked_add(i8::MAX), None);
+		assert_eq!(h.saturating_add(i8::MAX), Height::MAX);
+		assert_eq!(h.checked_sub(95), Some(Height::MIN));
+		assert_eq!(h.checked_sub(i8::MIN), None);
+		assert_eq!(h.saturating_sub(i8::MIN), Height::MAX);
+		assert_eq!(Height::MIN.saturating_sub(1), Height::MIN);
+	}
+
 	#[test]
 	fn parse_height_list() {
-		let r = many1(Height::parse)("(-8)22211000(-1)(-2)(-2)(-2)(-1)00").unwrap();
+		let r =
+			nom::multi::many1(Height::parse)("(-8)22211000(-1)(-2)(-2)(-2)(-1)00")
+				.unwrap();
 		println!("{:?}", r);
 	}
 
@@ -210,7 +632,7 @@ mod test {
 		assert_eq!(Prefab::parse("H").unwrap().1, Prefab::Hideous);
 	}
 
-	const TEST_MAP: &'static str = r#"222211000(-1)(-2)(-2)(-2)(-1)00
+	const TEST_MAP: &str = r#"222211000(-1)(-2)(-2)(-2)(-1)00
 2222(-15)(-15)(-15)00(-15)(-15)(-15)(-15)(-15)0(-15)
 (-15)(-15)(-15)(-15)(-15)(-15)(-15)00(-15)(-15)(-15)(-15)(-15)0(-15)
 (-15)(-15)(-15)(-15)(-15)(-15)(-15)00(-15)(-15)(-15)(-15)(-15)0(-15)
@@ -244,6 +666,15 @@ pppJJJJnnJJ0s000
 pppssssppJJ0s0H0
 pppJJJJppnn0s000"#;
 
+	/// A small pattern that no turn or mirror leaves the same.
+	pub(crate) fn lopsided() -> Map {
+		let mut map = Map::default();
+		map.heights.0[0][1] = Height(-15);
+		map.heights.0[2][5] = Height(7);
+		map.prefabs.0[3][1] = Prefab::Stairs;
+		map
+	}
+
 	#[test]
 	fn parse_in() {
 		let parsed = Map::parse(TEST_MAP).unwrap().1;
@@ -257,4 +688,192 @@ pppJJJJppnn0s000"#;
 		let serialized = parsed.to_string();
 		assert_eq!(serialized, TEST_MAP.to_string());
 	}
+
+	#[test]
+	fn from_str() {
+		assert_eq!(Map::from_str(TEST_MAP), Ok(Map::parse(TEST_MAP).unwrap().1));
+	}
+
+	#[test]
+	fn from_str_short_row() {
+		let input = TEST_MAP.replacen("222221000012100(-15)", "2222210", 1);
+		let err = Map::from_str(&input).unwrap_err();
+		assert_eq!(err.section, Section::Heights);
+		assert_eq!((err.line, err.column), (5, 8));
+		assert_eq!(err.kind, ParseErrorKind::Expected(Expected::Height));
+		assert_eq!(err.found, Some('\n'));
+	}
+
+	#[test]
+	fn from_str_out_of_range() {
+		let input = TEST_MAP.replacen("(-15)", "(300)", 1);
+		let err = Map::from_str(&input).unwrap_err();
+		assert_eq!((err.line, err.column), (2, 6));
+		assert_eq!(
+			err.kind,
+			ParseErrorKind::HeightOutOfRange("300".to_string())
+		);
+	}
+
+	#[test]
+	fn from_str_bad_prefab() {
+		let input = TEST_MAP.replacen("pppJJJJppnn0s000", "pppJJJJppnn0#000", 1);
+		let err = Map::from_str(&input).unwrap_err();
+		assert_eq!(err.section, Section::Prefabs);
+		assert_eq!((err.line, err.column), (33, 13));
+		assert_eq!(err.kind, ParseErrorKind::Expected(Expected::Prefab));
+		assert_eq!(
+			err.to_string(),
+			"33:13 (prefabs): expected a prefab character (a letter or digit), found `#`"
+		);
+	}
+
+	#[test]
+	fn unknown_prefabs() {
+		assert_eq!(
+			Prefab::parse("x").unwrap().1,
+			Prefab::Unknown(UnknownChar('x'))
+		);
+		assert_eq!(
+			Prefab::parse("7").unwrap().1,
+			Prefab::Unknown(UnknownChar('7'))
+		);
+		assert!(Prefab::parse("#").is_err());
+		assert!(Prefab::parse(" ").is_err());
+
+		let input = TEST_MAP.replacen("pppJJJJppnn0s000", "pppJJJJppnn0xQ00", 1);
+		let map = Map::from_str(&input).unwrap();
+		assert_eq!(
+			map.prefabs.get(12, 15),
+			Some(&Prefab::Unknown(UnknownChar('x')))
+		);
+		assert_eq!(
+			map.prefabs.get(13, 15),
+			Some(&Prefab::Unknown(UnknownChar('Q')))
+		);
+		assert!(Prefab::Unknown(UnknownChar('Q')).is_unknown());
+		assert_eq!(UnknownChar::new('Q').map(UnknownChar::get), Some('Q'));
+		for c in ['H', '0', '#', 'é'].iter() {
+			assert_eq!(UnknownChar::try_from(*c), Err(InvalidUnknownChar(*c)));
+		}
+		assert_eq!(map.to_string(), input);
+	}
+
+	#[test]
+	fn from_str_missing_separator() {
+		let input = TEST_MAP.replacen("\n\n", "\n", 1);
+		let err = Map::from_str(&input).unwrap_err();
+		assert_eq!((err.line, err.column), (17, 1));
+		assert_eq!(err.kind, ParseErrorKind::Expected(Expected::BlankLine));
+	}
+
+	#[test]
+	fn recovering_clean() {
+		let (map, errors) = Map::from_str_recovering(TEST_MAP);
+		assert!(errors.is_empty(), "{:?}", errors);
+		assert_eq!(map, Map::from_str(TEST_MAP).unwrap());
+	}
+
+	#[test]
+	fn recovering_reports_everything() {
+		let input = TEST_MAP
+			.replacen("222221000012100(-15)", "22x221000012100(-15)", 1)
+			.replacen("555321000(-15)(-15)00111", "555321000(300)(-15)001110", 1)
+			.replacen("JJppsnsnnJJ00sss", "JJppsnsnnJJ00s", 1)
+			.replacen("\n\n", "\n", 1);
+		let (map, errors) = Map::from_str_recovering(&input);
+		let found: Vec<_> = errors.iter().map(|e| (e.line, &e.kind)).collect();
+		assert_eq!(
+			found,
+			vec![
+				(17, &ParseErrorKind::Expected(Expected::BlankLine)),
+				(5, &ParseErrorKind::Expected(Expected::Height)),
+				(15, &ParseErrorKind::HeightOutOfRange("300".to_string())),
+				(15, &ParseErrorKind::RowLength(17)),
+				(26, &ParseErrorKind::RowLength(14)),
+			]
+		);
+		assert_eq!(map.heights.get(2, 4), Some(&Height::default()));
+		assert_eq!(map.heights.get(3, 4), Some(&Height(2)));
+		assert_eq!(map.prefabs.get(13, 9), Some(&Prefab::Stairs));
+		assert_eq!(map.prefabs.get(14, 9), Some(&Prefab::None));
+	}
+
+	#[test]
+	fn recovering_skips_bom() {
+		let input = format!("\u{feff}{}", TEST_MAP);
+		let (map, errors) = Map::from_str_recovering(&input);
+		assert!(errors.is_empty(), "{:?}", errors);
+		assert_eq!(map, Map::from_str(TEST_MAP).unwrap());
+
+		let broken =
+			input.replacen("222221000012100(-15)", "22x221000012100(-15)", 1);
+		let (_, errors) = Map::from_str_recovering(&broken);
+		assert_eq!(errors, vec![Map::from_str(&broken).unwrap_err()]);
+	}
+
+	#[test]
+	fn strict_accepts_canonical() {
+		let map = Map::from_str_with(TEST_MAP, ParseOptions::strict()).unwrap();
+		assert_eq!(map.to_string(), TEST_MAP);
+	}
+
+	#[test]
+	fn strict_rejects_non_canonical() {
+		let strict = |input: &str| {
+			Map::from_str_with(input, ParseOptions::strict()).unwrap_err()
+		};
+
+		let err = strict(&TEST_MAP.replace('\n', "\r\n"));
+		assert_eq!(err.kind, ParseErrorKind::CarriageReturn);
+		assert_eq!((err.line, err.column), (1, 32));
+
+		let err = strict(&format!("{}\n", TEST_MAP));
+		assert_eq!(err.kind, ParseErrorKind::TrailingContent);
+		assert_eq!((err.line, err.column), (33, 17));
+
+		let err = strict(&format!("\u{feff}{}", TEST_MAP));
+		assert_eq!(err.kind, ParseErrorKind::ByteOrderMark);
+
+		let err = strict(&format!(" {}", TEST_MAP));
+		assert_eq!(err.kind, ParseErrorKind::Expected(Expected::Height));
+	}
+
+	#[test]
+	fn lenient_accepts_windows_files() {
+		let input = format!("\u{feff}{}\r\n", TEST_MAP.replace('\n', "\r\n"));
+		assert_eq!(
+			Map::from_str_with(&input, ParseOptions::lenient()),
+			Map::from_str_with(TEST_MAP, ParseOptions::strict())
+		);
+	}
+
+	#[test]
+	fn trailing_content() {
+		let input = format!("{}\nJJJJ", TEST_MAP);
+		let err = Map::from_str(&input).unwrap_err();
+		assert_eq!(err.kind, ParseErrorKind::TrailingContent);
+		assert_eq!((err.line, err.column, err.found), (33, 17, Some('\n')));
+
+		let options = ParseOptions {
+			allow_trailing_content: true,
+			..ParseOptions::lenient()
+		};
+		assert!(Map::from_str_with(&input, options).is_ok());
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_round_trip() {
+		let mut map = Map::parse(TEST_MAP).unwrap().1;
+		map.prefabs.0[4][7] = Prefab::Unknown(UnknownChar('x'));
+
+		let toml = toml::to_string(&map).unwrap();
+		assert_eq!(toml::from_str::<Map>(&toml).unwrap(), map);
+		let json = serde_json::to_string(&map).unwrap();
+		assert_eq!(serde_json::from_str::<Map>(&json).unwrap(), map);
+
+		assert!(serde_json::from_str::<Prefab>("\"n\"").is_ok());
+		assert!(serde_json::from_str::<Prefab>("\"#\"").is_err());
+	}
 }
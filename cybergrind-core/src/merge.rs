@@ -0,0 +1,240 @@
+use std::{fmt, str::FromStr};
+
+use crate::{Grid, Height, Map, Parsable, Prefab};
+
+/// A tile both sides changed to different values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict<T> {
+	pub x: usize,
+	pub y: usize,
+	pub base: T,
+	pub ours: T,
+	pub theirs: T,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Conflicts {
+	pub heights: Vec<Conflict<Height>>,
+	pub prefabs: Vec<Conflict<Prefab>>,
+}
+
+impl Conflicts {
+	pub fn is_empty(&self) -> bool {
+		self.heights.is_empty() && self.prefabs.is_empty()
+	}
+
+	pub fn len(&self) -> usize {
+		self.heights.len() + self.prefabs.len()
+	}
+}
+
+/// One conflict per line, as `<layer> <x> <y> <base> <ours> <theirs>` with
+/// the values written the same way as in a pattern file.
+impl fmt::Display for Conflicts {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for c in self.heights.iter() {
+			writeln!(
+				f,
+				"height {} {} {} {} {}",
+				c.x, c.y, c.base, c.ours, c.theirs
+			)?;
+		}
+		for c in self.prefabs.iter() {
+			writeln!(
+				f,
+				"prefab {} {} {} {} {}",
+				c.x, c.y, c.base, c.ours, c.theirs
+			)?;
+		}
+		Ok(())
+	}
+}
+
+/// A line of a conflicts file that couldn't be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidConflict {
+	/// 1-based line number.
+	pub line: usize,
+}
+
+impl fmt::Display for InvalidConflict {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid conflict on line {}", self.line)
+	}
+}
+
+impl std::error::Error for InvalidConflict {}
+
+fn parse_conflict<T: Parsable>(fields: &[&str]) -> Option<Conflict<T>> {
+	fn value<T: Parsable>(field: &str) -> Option<T> {
+		match T::parse(field) {
+			Ok(("", v)) => Some(v),
+			_ => None,
+		}
+	}
+	match fields {
+		[x, y, base, ours, theirs] => Some(Conflict {
+			x: x.parse().ok().filter(|x| *x < 16)?,
+			y: y.parse().ok().filter(|y| *y < 16)?,
+			base: value(base)?,
+			ours: value(ours)?,
+			theirs: value(theirs)?,
+		}),
+		_ => None,
+	}
+}
+
+impl FromStr for Conflicts {
+	type Err = InvalidConflict;
+
+	fn from_str(s: &str) -> Result<Self, InvalidConflict> {
+		let mut conflicts = Self::default();
+		for (i, line) in s.lines().enumerate() {
+			let fields: Vec<&str> = line.split_whitespace().collect();
+			let parsed = match fields.split_first() {
+				None => Some(()),
+				Some((&"height", rest)) => {
+					parse_conflict(rest).map(|c| conflicts.heights.push(c))
+				}
+				Some((&"prefab", rest)) => {
+					parse_conflict(rest).map(|c| conflicts.prefabs.push(c))
+				}
+				Some(_) => None,
+			};
+			if parsed.is_none() {
+				return Err(InvalidConflict { line: i + 1 });
+			}
+		}
+		Ok(conflicts)
+	}
+}
+
+/// The result of [`merge`]. Conflicting tiles are left as they are in
+/// `ours`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+	pub map: Map,
+	pub conflicts: Conflicts,
+}
+
+fn merge_grid<T: Parsable + Copy + PartialEq>(
+	base: &Grid<T>,
+	ours: &Grid<T>,
+	theirs: &Grid<T>,
+	conflicts: &mut Vec<Conflict<T>>,
+) -> Grid<T> {
+	let mut merged = *ours;
+	for ((x, y), base) in base.iter() {
+		let (ours, theirs) = (ours.0[y][x], theirs.0[y][x]);
+		if ours == *base {
+			merged.0[y][x] = theirs;
+		} else if theirs != *base && theirs != ours {
+			conflicts.push(Conflict {
+				x,
+				y,
+				base: *base,
+				ours,
+				theirs,
+			});
+		}
+	}
+	merged
+}
+
+/// Three-way merges two maps descended from `base`, tile by tile. Heights
+/// and prefabs are merged separately, so one side changing a tile's height
+/// and the other its prefab doesn't conflict.
+pub fn merge(base: &Map, ours: &Map, theirs: &Map) -> Merge {
+	let mut conflicts = Conflicts::default();
+	let map = Map {
+		heights: merge_grid(
+			&base.heights,
+			&ours.heights,
+			&theirs.heights,
+			&mut conflicts.heights,
+		),
+		prefabs: merge_grid(
+			&base.prefabs,
+			&ours.prefabs,
+			&theirs.prefabs,
+			&mut conflicts.prefabs,
+		),
+	};
+	Merge { map, conflicts }
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn clean_merge() {
+		let base = Map::default();
+		let mut ours = base;
+		ours.heights.0[0][0] = Height(3);
+		ours.prefabs.0[5][5] = Prefab::Melee;
+		let mut theirs = base;
+		theirs.heights.0[15][15] = Height(-15);
+		theirs.prefabs.0[5][5] = Prefab::Melee;
+		theirs.prefabs.0[0][0] = Prefab::Stairs;
+
+		let merged = merge(&base, &ours, &theirs);
+		assert!(merged.conflicts.is_empty());
+		assert_eq!(merged.map.heights.get(0, 0), Some(&Height(3)));
+		assert_eq!(merged.map.heights.get(15, 15), Some(&Height(-15)));
+		assert_eq!(merged.map.prefabs.get(5, 5), Some(&Prefab::Melee));
+		assert_eq!(merged.map.prefabs.get(0, 0), Some(&Prefab::Stairs));
+	}
+
+	#[test]
+	fn conflicting_merge() {
+		let base = Map::default();
+		let mut ours = base;
+		ours.heights.0[2][1] = Height(3);
+		let mut theirs = base;
+		theirs.heights.0[2][1] = Height(-15);
+		theirs.prefabs.0[2][1] = Prefab::JumpPad;
+
+		let merged = merge(&base, &ours, &theirs);
+		assert_eq!(
+			merged.conflicts.heights,
+			vec![Conflict {
+				x: 1,
+				y: 2,
+				base: Height(0),
+				ours: Height(3),
+				theirs: Height(-15),
+			}]
+		);
+		assert!(merged.conflicts.prefabs.is_empty());
+		assert_eq!(merged.map.heights.get(1, 2), Some(&Height(3)));
+		assert_eq!(merged.map.prefabs.get(1, 2), Some(&Prefab::JumpPad));
+	}
+
+	#[test]
+	fn conflicts_round_trip() {
+		let conflicts = Conflicts {
+			heights: vec![Conflict {
+				x: 1,
+				y: 2,
+				base: Height(0),
+				ours: Height(3),
+				theirs: Height(-15),
+			}],
+			prefabs: vec![Conflict {
+				x: 15,
+				y: 0,
+				base: Prefab::None,
+				ours: Prefab::Stairs,
+				theirs: Prefab::Hideous,
+			}],
+		};
+		let text = conflicts.to_string();
+		assert_eq!(text, "height 1 2 0 3 (-15)\nprefab 15 0 0 s H\n");
+		assert_eq!(text.parse(), Ok(conflicts));
+		assert_eq!(
+			"height 1 2 0 3\n".parse::<Conflicts>(),
+			Err(InvalidConflict { line: 1 })
+		);
+	}
+}
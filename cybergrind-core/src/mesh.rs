@@ -0,0 +1,439 @@
+//! Arena geometry as triangle meshes, to look at patterns in other programs.
+//!
+//! The layout is the editor's: every tile is a 1x1 pillar 16 units tall with
+//! its top at the tile's height, and the arena is centered on the origin with
+//! y up.
+
+use std::{
+	fmt::Write as _,
+	io::{self, Write},
+};
+
+use crate::{Map, Prefab};
+
+pub const PILLAR_HEIGHT: f32 = 16.0;
+const PILLAR_COLOR: [f32; 4] = [0.75, 0.75, 0.75, 1.0];
+/// Prefabs are shown as small cubes on top of their pillar.
+const MARKER_SIZE: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+	pub name: String,
+	/// RGBA, each from 0 to 1.
+	pub color: [f32; 4],
+	pub positions: Vec<[f32; 3]>,
+	pub normals: Vec<[f32; 3]>,
+	/// Triangles, counter-clockwise seen from the outside.
+	pub indices: Vec<u32>,
+}
+
+impl Mesh {
+	fn new(name: &str, color: [f32; 4]) -> Self {
+		Self {
+			name: name.to_string(),
+			color,
+			positions: Vec::new(),
+			normals: Vec::new(),
+			indices: Vec::new(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.indices.is_empty()
+	}
+
+	/// Adds a closed, axis-aligned box with its own vertices for each face, so
+	/// the edges stay sharp.
+	fn add_box(&mut self, min: [f32; 3], max: [f32; 3]) {
+		for axis in 0..3 {
+			let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+			for &positive in [false, true].iter() {
+				let mut normal = [0.0; 3];
+				normal[axis] = if positive { 1.0 } else { -1.0 };
+				let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+				if !positive {
+					corners.reverse();
+				}
+				let start = self.positions.len() as u32;
+				for (cu, cv) in corners.iter() {
+					let mut p = if positive { max } else { min };
+					p[u] = if *cu == 0 { min[u] } else { max[u] };
+					p[v] = if *cv == 0 { min[v] } else { max[v] };
+					self.positions.push(p);
+					self.normals.push(normal);
+				}
+				self
+					.indices
+					.extend([0, 1, 2, 0, 2, 3].iter().map(|i| start + i));
+			}
+		}
+	}
+
+	fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+		let mut min = [f32::INFINITY; 3];
+		let mut max = [f32::NEG_INFINITY; 3];
+		for p in self.positions.iter() {
+			for i in 0..3 {
+				min[i] = min[i].min(p[i]);
+				max[i] = max[i].max(p[i]);
+			}
+		}
+		(min, max)
+	}
+}
+
+fn prefab_name(prefab: Prefab) -> String {
+	format!("{:?}", prefab).to_lowercase()
+}
+
+/// Which of the marker meshes a prefab goes in. Unknown prefabs all share
+/// the last one.
+fn marker(prefab: Prefab) -> Option<usize> {
+	match prefab {
+		Prefab::Unknown(_) => Some(Prefab::ALL.len() - 1),
+		prefab => Prefab::ALL[1..].iter().position(|p| *p == prefab),
+	}
+}
+
+impl Map {
+	/// The pillars as one mesh, then one mesh of markers for each kind of
+	/// prefab on the map, with every unknown prefab in one last mesh.
+	pub fn to_meshes(&self) -> Vec<Mesh> {
+		let mut pillars = Mesh::new("pillars", PILLAR_COLOR);
+		let mut markers: Vec<Mesh> = Prefab::ALL[1..]
+			.iter()
+			.map(|prefab| (prefab_name(*prefab), prefab.color()))
+			.chain(std::iter::once((
+				"unknown".to_string(),
+				Prefab::UNKNOWN_COLOR,
+			)))
+			.map(|(name, [r, g, b])| {
+				let color = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0];
+				Mesh::new(&name, color)
+			})
+			.collect();
+
+		for ((x, y), height) in self.heights.iter() {
+			let (left, front) = (x as f32 - 8.0, y as f32 - 8.0);
+			let top = height.0 as f32;
+			pillars.add_box(
+				[left, top - PILLAR_HEIGHT, front],
+				[left + 1.0, top, front + 1.0],
+			);
+			let prefab = self.prefabs.0[y][x];
+			if let Some(i) = marker(prefab) {
+				let inset = (1.0 - MARKER_SIZE) / 2.0;
+				markers[i].add_box(
+					[left + inset, top, front + inset],
+					[left + 1.0 - inset, top + MARKER_SIZE, front + 1.0 - inset],
+				);
+			}
+		}
+
+		let mut meshes = vec![pillars];
+		meshes.extend(markers.into_iter().filter(|m| !m.is_empty()));
+		meshes
+	}
+}
+
+/// Writes meshes as a Wavefront OBJ, one object each. Colors go in a
+/// separate material library, see [`write_mtl`], which the OBJ refers to as
+/// `mtl_name` if given.
+pub fn write_obj<W: Write>(
+	meshes: &[Mesh],
+	mtl_name: Option<&str>,
+	mut w: W,
+) -> io::Result<()> {
+	writeln!(w, "# Cyber Grind pattern")?;
+	if let Some(mtl_name) = mtl_name {
+		writeln!(w, "mtllib {}", mtl_name)?;
+	}
+	// OBJ indices are 1-based and count from the start of the file.
+	let mut offset = 1;
+	for mesh in meshes {
+		writeln!(w, "o {}", mesh.name)?;
+		if mtl_name.is_some() {
+			writeln!(w, "usemtl {}", mesh.name)?;
+		}
+		for [x, y, z] in mesh.positions.iter() {
+			writeln!(w, "v {} {} {}", x, y, z)?;
+		}
+		for [x, y, z] in mesh.normals.iter() {
+			writeln!(w, "vn {} {} {}", x, y, z)?;
+		}
+		for t in mesh.indices.chunks(3) {
+			let (a, b, c) = (t[0] + offset, t[1] + offset, t[2] + offset);
+			writeln!(w, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c)?;
+		}
+		offset += mesh.positions.len() as u32;
+	}
+	Ok(())
+}
+
+/// Writes the material library for [`write_obj`], one flat colored material
+/// per mesh.
+pub fn write_mtl<W: Write>(meshes: &[Mesh], mut w: W) -> io::Result<()> {
+	for mesh in meshes {
+		let [r, g, b, a] = mesh.color;
+		writeln!(w, "newmtl {}", mesh.name)?;
+		writeln!(w, "Kd {} {} {}", r, g, b)?;
+		writeln!(w, "d {}", a)?;
+	}
+	Ok(())
+}
+
+const GLB_MAGIC: u32 = 0x4654_6c67;
+const GLB_JSON: u32 = 0x4e4f_534a;
+const GLB_BIN: u32 = 0x004e_4942;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+fn pad(bytes: &mut Vec<u8>, with: u8) {
+	let padding = (4 - bytes.len() % 4) % 4;
+	bytes.resize(bytes.len() + padding, with);
+}
+
+/// Writes meshes as binary glTF, one node each, with everything in a single
+/// file.
+pub fn write_glb<W: Write>(meshes: &[Mesh], mut w: W) -> io::Result<()> {
+	let mut bin: Vec<u8> = Vec::new();
+	let mut views = Vec::new();
+	let mut accessors = Vec::new();
+	let mut gltf_meshes = Vec::new();
+	let mut materials = Vec::new();
+	let mut nodes = Vec::new();
+
+	for (i, mesh) in meshes.iter().enumerate() {
+		let mut view = |bin: &mut Vec<u8>, data: &[u8], target: u32| {
+			let offset = bin.len();
+			bin.extend_from_slice(data);
+			pad(bin, 0);
+			views.push(format!(
+				r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+				offset,
+				data.len(),
+				target
+			));
+			views.len() - 1
+		};
+		let floats = |vs: &[[f32; 3]]| -> Vec<u8> {
+			vs.iter()
+				.flatten()
+				.flat_map(|f| f.to_le_bytes().to_vec())
+				.collect()
+		};
+		let indices: Vec<u8> = mesh
+			.indices
+			.iter()
+			.flat_map(|i| i.to_le_bytes().to_vec())
+			.collect();
+
+		let positions = view(&mut bin, &floats(&mesh.positions), ARRAY_BUFFER);
+		let normals = view(&mut bin, &floats(&mesh.normals), ARRAY_BUFFER);
+		let indices = view(&mut bin, &indices, ELEMENT_ARRAY_BUFFER);
+
+		let (min, max) = mesh.bounds();
+		let first = accessors.len();
+		accessors.push(format!(
+			r#"{{"bufferView":{},"componentType":{},"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+			positions,
+			FLOAT,
+			mesh.positions.len(),
+			min[0],
+			min[1],
+			min[2],
+			max[0],
+			max[1],
+			max[2]
+		));
+		accessors.push(format!(
+			r#"{{"bufferView":{},"componentType":{},"count":{},"type":"VEC3"}}"#,
+			normals,
+			FLOAT,
+			mesh.normals.len()
+		));
+		accessors.push(format!(
+			r#"{{"bufferView":{},"componentType":{},"count":{},"type":"SCALAR"}}"#,
+			indices,
+			UNSIGNED_INT,
+			mesh.indices.len()
+		));
+
+		let [r, g, b, a] = mesh.color;
+		materials.push(format!(
+			r#"{{"name":"{}","pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},{}],"metallicFactor":0,"roughnessFactor":1}}}}"#,
+			mesh.name, r, g, b, a
+		));
+		gltf_meshes.push(format!(
+			r#"{{"name":"{}","primitives":[{{"attributes":{{"POSITION":{},"NORMAL":{}}},"indices":{},"material":{}}}]}}"#,
+			mesh.name,
+			first,
+			first + 1,
+			first + 2,
+			i
+		));
+		nodes.push(format!(r#"{{"name":"{}","mesh":{}}}"#, mesh.name, i));
+	}
+
+	let mut json = String::new();
+	write!(
+		json,
+		r#"{{"asset":{{"version":"2.0","generator":"cybergrind"}},"scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#,
+		(0..nodes.len())
+			.map(|i| i.to_string())
+			.collect::<Vec<_>>()
+			.join(","),
+		nodes.join(","),
+		gltf_meshes.join(","),
+		materials.join(","),
+		accessors.join(","),
+		views.join(","),
+		bin.len()
+	)
+	.unwrap();
+	let mut json = json.into_bytes();
+	pad(&mut json, b' ');
+
+	let length = 12 + 8 + json.len() + 8 + bin.len();
+	for word in [GLB_MAGIC, 2, length as u32].iter() {
+		w.write_all(&word.to_le_bytes())?;
+	}
+	w.write_all(&(json.len() as u32).to_le_bytes())?;
+	w.write_all(&GLB_JSON.to_le_bytes())?;
+	w.write_all(&json)?;
+	w.write_all(&(bin.len() as u32).to_le_bytes())?;
+	w.write_all(&GLB_BIN.to_le_bytes())?;
+	w.write_all(&bin)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Height, Parsable, UnknownChar};
+	use std::collections::HashMap;
+
+	fn sample() -> Map {
+		let mut map = Map::default();
+		map.heights.0[2][3] = Height(5);
+		map.heights.0[2][4] = Height(-15);
+		map.prefabs.0[2][3] = Prefab::JumpPad;
+		map.prefabs.0[7][7] = Prefab::Melee;
+		map.prefabs.0[8][8] = Prefab::Melee;
+		map
+	}
+
+	#[test]
+	fn meshes() {
+		let meshes = sample().to_meshes();
+		let names: Vec<&str> = meshes.iter().map(|m| m.name.as_str()).collect();
+		assert_eq!(names, vec!["pillars", "melee", "jumppad"]);
+		assert_eq!(meshes[0].indices.len(), 256 * 36);
+		assert_eq!(meshes[1].indices.len(), 2 * 36);
+
+		let (min, max) = meshes[0].bounds();
+		assert_eq!(min, [-8.0, -15.0 - PILLAR_HEIGHT, -8.0]);
+		assert_eq!(max, [8.0, 5.0, 8.0]);
+		let (min, _) = meshes[2].bounds();
+		assert_eq!(min, [3.0 - 8.0 + 0.25, 5.0, 2.0 - 8.0 + 0.25]);
+
+		let mut map = sample();
+		map.prefabs.0[0][0] = Prefab::Unknown(UnknownChar('x'));
+		map.prefabs.0[0][1] = Prefab::Unknown(UnknownChar('y'));
+		let meshes = map.to_meshes();
+		assert_eq!(meshes.last().unwrap().name, "unknown");
+		assert_eq!(meshes.last().unwrap().indices.len(), 2 * 36);
+	}
+
+	/// Every edge of a closed mesh is walked once in each direction, and
+	/// each triangle faces the way its normals do.
+	#[test]
+	fn closed_and_outward() {
+		for mesh in sample().to_meshes() {
+			let key = |i: u32| {
+				let p = mesh.positions[i as usize];
+				[p[0].to_bits(), p[1].to_bits(), p[2].to_bits()]
+			};
+			let mut edges: HashMap<_, i32> = HashMap::new();
+			for t in mesh.indices.chunks(3) {
+				for k in 0..3 {
+					let (a, b) = (key(t[k]), key(t[(k + 1) % 3]));
+					*edges.entry((a, b)).or_default() += 1;
+					*edges.entry((b, a)).or_default() -= 1;
+				}
+
+				let [a, b, c] = [t[0], t[1], t[2]].map(|i| mesh.positions[i as usize]);
+				let (e1, e2) = (
+					[b[0] - a[0], b[1] - a[1], b[2] - a[2]],
+					[c[0] - a[0], c[1] - a[1], c[2] - a[2]],
+				);
+				let cross = [
+					e1[1] * e2[2] - e1[2] * e2[1],
+					e1[2] * e2[0] - e1[0] * e2[2],
+					e1[0] * e2[1] - e1[1] * e2[0],
+				];
+				let n = mesh.normals[t[0] as usize];
+				assert!(cross[0] * n[0] + cross[1] * n[1] + cross[2] * n[2] > 0.0);
+			}
+			assert!(
+				edges.values().all(|n| *n == 0),
+				"{} isn't closed",
+				mesh.name
+			);
+		}
+	}
+
+	#[test]
+	fn obj() {
+		let meshes = sample().to_meshes();
+		let mut obj = Vec::new();
+		write_obj(&meshes, Some("arena.mtl"), &mut obj).unwrap();
+		let obj = String::from_utf8(obj).unwrap();
+		let count =
+			|prefix: &str| obj.lines().filter(|l| l.starts_with(prefix)).count();
+		assert_eq!(count("o "), 3);
+		assert_eq!(count("v "), (256 + 3) * 24);
+		assert_eq!(count("f "), (256 + 3) * 12);
+		assert!(obj.contains("mtllib arena.mtl\no pillars\nusemtl pillars\n"));
+		let last = obj.lines().last().unwrap();
+		assert!(last.ends_with(&format!(" {0}//{0}", (256 + 3) * 24)));
+
+		let mut mtl = Vec::new();
+		write_mtl(&meshes, &mut mtl).unwrap();
+		let mtl = String::from_utf8(mtl).unwrap();
+		assert!(mtl.starts_with("newmtl pillars\nKd 0.75 0.75 0.75\nd 1\n"));
+	}
+
+	#[test]
+	fn glb() {
+		let meshes = sample().to_meshes();
+		let mut glb = Vec::new();
+		write_glb(&meshes, &mut glb).unwrap();
+		let word = |i: usize| {
+			u32::from_le_bytes([glb[i], glb[i + 1], glb[i + 2], glb[i + 3]])
+		};
+		assert_eq!(word(0), GLB_MAGIC);
+		assert_eq!(word(4), 2);
+		assert_eq!(word(8) as usize, glb.len());
+		assert_eq!(glb.len() % 4, 0);
+
+		let json_len = word(12) as usize;
+		assert_eq!(word(16), GLB_JSON);
+		let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+		assert!(json.contains(r#""nodes":[{"name":"pillars","mesh":0}"#));
+		assert!(json.contains(r#""min":[-8,-31,-8],"max":[8,5,8]"#));
+
+		let bin = 20 + json_len;
+		assert_eq!(word(bin + 4), GLB_BIN);
+		let expected: usize = meshes
+			.iter()
+			.map(|m| m.positions.len() * 24 + m.indices.len() * 4)
+			.sum();
+		assert_eq!(word(bin) as usize, expected);
+		assert!(json
+			.trim_end()
+			.ends_with(&format!(r#""buffers":[{{"byteLength":{}}}]}}"#, expected)));
+	}
+}
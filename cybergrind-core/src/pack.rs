@@ -0,0 +1,343 @@
+//! Packs: many patterns in one file, with their metadata and thumbnails, for
+//! handing out whole collections at once.
+//!
+//! A pack is the bytes `CGPACK`, a version byte and the number of entries as
+//! a little endian `u32`, then each entry as:
+//!
+//! - its name, as UTF-8 prefixed with its length as a `u16`,
+//! - the pattern file, the metadata as TOML and the thumbnail as a PNG, each
+//!   prefixed with its length as a `u32`. Missing thumbnails are empty.
+//!
+//! All lengths are little endian.
+
+use std::{
+	fmt, fs,
+	io::{self, Read, Write},
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use crate::{
+	document::{DocumentError, Metadata, PatternDocument},
+	Map,
+};
+
+const MAGIC: &[u8; 6] = b"CGPACK";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackEntry {
+	/// Names are used as file names when extracting, so they can't be empty
+	/// or have path separators in them.
+	pub name: String,
+	pub document: PatternDocument,
+	/// A PNG image of the pattern.
+	pub thumbnail: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub enum PackError {
+	Io(io::Error),
+	/// The file doesn't start like a pack.
+	NotAPack,
+	/// The pack was made by a newer version of this library.
+	Version(u8),
+	InvalidName(String),
+	Entry {
+		name: String,
+		error: DocumentError,
+	},
+}
+
+impl fmt::Display for PackError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PackError::Io(e) => e.fmt(f),
+			PackError::NotAPack => write!(f, "not a pattern pack"),
+			PackError::Version(v) => {
+				write!(f, "pack is version {}, expected {}", v, VERSION)
+			}
+			PackError::InvalidName(name) => {
+				write!(f, "invalid pack entry name {:?}", name)
+			}
+			PackError::Entry { name, error } => write!(f, "{}: {}", name, error),
+		}
+	}
+}
+
+impl std::error::Error for PackError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			PackError::Io(e) => Some(e),
+			PackError::Entry { error, .. } => Some(error),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for PackError {
+	fn from(e: io::Error) -> Self {
+		PackError::Io(e)
+	}
+}
+
+/// Names are written with a 16-bit length, and can't leave the folder
+/// they're extracted to.
+fn valid_name(name: &str) -> bool {
+	!name.is_empty()
+		&& name.len() <= u16::MAX as usize
+		&& name != "."
+		&& name != ".."
+		&& !name.contains(&['/', '\\', '\0'][..])
+}
+
+impl PackEntry {
+	/// The files this entry is extracted to in `dir`: the pattern, and the
+	/// thumbnail if it has one. Metadata goes next to the pattern as usual.
+	pub fn paths(&self, dir: &Path) -> (PathBuf, PathBuf) {
+		(
+			dir.join(format!("{}.cgp", self.name)),
+			dir.join(format!("{}.thumbnail.png", self.name)),
+		)
+	}
+
+	pub fn extract(&self, dir: &Path) -> Result<(), PackError> {
+		let (pattern, thumbnail) = self.paths(dir);
+		let entry_error = |error| PackError::Entry {
+			name: self.name.clone(),
+			error,
+		};
+		fs::write(&pattern, self.document.map.to_string())?;
+		self
+			.document
+			.metadata
+			.write(&pattern)
+			.map_err(entry_error)?;
+		if let Some(png) = &self.thumbnail {
+			fs::write(thumbnail, png)?;
+		}
+		Ok(())
+	}
+}
+
+/// A pack's entries, in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pack {
+	entries: Vec<PackEntry>,
+}
+
+impl Pack {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn entries(&self) -> &[PackEntry] {
+		&self.entries
+	}
+
+	pub fn get(&self, name: &str) -> Option<&PackEntry> {
+		self.entries.iter().find(|e| e.name == name)
+	}
+
+	/// Adds `entry`, replacing and returning any entry with the same name.
+	pub fn add(
+		&mut self,
+		entry: PackEntry,
+	) -> Result<Option<PackEntry>, PackError> {
+		if !valid_name(&entry.name) {
+			return Err(PackError::InvalidName(entry.name));
+		}
+		match self.entries.iter_mut().find(|e| e.name == entry.name) {
+			Some(old) => Ok(Some(std::mem::replace(old, entry))),
+			None => {
+				self.entries.push(entry);
+				Ok(None)
+			}
+		}
+	}
+
+	pub fn remove(&mut self, name: &str) -> Option<PackEntry> {
+		let i = self.entries.iter().position(|e| e.name == name)?;
+		Some(self.entries.remove(i))
+	}
+
+	/// Writes every entry into `dir`, see [`PackEntry::extract`].
+	pub fn extract(&self, dir: &Path) -> Result<(), PackError> {
+		fs::create_dir_all(dir)?;
+		for entry in &self.entries {
+			entry.extract(dir)?;
+		}
+		Ok(())
+	}
+
+	pub fn read<R: Read>(mut r: R) -> Result<Self, PackError> {
+		let mut magic = [0; 6];
+		r.read_exact(&mut magic).map_err(|_| PackError::NotAPack)?;
+		if &magic != MAGIC {
+			return Err(PackError::NotAPack);
+		}
+		let version = read_bytes::<1, _>(&mut r)?[0];
+		if version != VERSION {
+			return Err(PackError::Version(version));
+		}
+		let count = u32::from_le_bytes(read_bytes(&mut r)?);
+
+		let mut pack = Pack::new();
+		for _ in 0..count {
+			let length = u16::from_le_bytes(read_bytes(&mut r)?);
+			let name =
+				String::from_utf8(read_vec(&mut r, length as u32)?).map_err(|e| {
+					PackError::InvalidName(String::from_utf8_lossy(e.as_bytes()).into())
+				})?;
+			let entry_error = |error| PackError::Entry {
+				name: name.clone(),
+				error,
+			};
+			let pattern = read_text(&mut r).map_err(entry_error)?;
+			let map = Map::from_str(&pattern)
+				.map_err(|e| entry_error(DocumentError::Pattern(e)))?;
+			let metadata =
+				Metadata::from_toml(&read_text(&mut r).map_err(entry_error)?)
+					.map_err(|e| entry_error(DocumentError::Metadata(e)))?;
+			let length = u32::from_le_bytes(read_bytes(&mut r)?);
+			let thumbnail = Some(read_vec(&mut r, length)?).filter(|t| !t.is_empty());
+			pack.add(PackEntry {
+				name,
+				document: PatternDocument { map, metadata },
+				thumbnail,
+			})?;
+		}
+		Ok(pack)
+	}
+
+	pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+		w.write_all(MAGIC)?;
+		w.write_all(&[VERSION])?;
+		w.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+		for entry in &self.entries {
+			w.write_all(&(entry.name.len() as u16).to_le_bytes())?;
+			w.write_all(entry.name.as_bytes())?;
+			for bytes in [
+				entry.document.map.to_string().as_bytes(),
+				entry.document.metadata.to_toml().as_bytes(),
+				entry.thumbnail.as_deref().unwrap_or_default(),
+			]
+			.iter()
+			{
+				w.write_all(&(bytes.len() as u32).to_le_bytes())?;
+				w.write_all(bytes)?;
+			}
+		}
+		w.flush()
+	}
+}
+
+fn read_bytes<const N: usize, R: Read>(r: &mut R) -> io::Result<[u8; N]> {
+	let mut bytes = [0; N];
+	r.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+fn read_vec<R: Read>(r: &mut R, length: u32) -> io::Result<Vec<u8>> {
+	let mut bytes = Vec::new();
+	r.take(length as u64).read_to_end(&mut bytes)?;
+	if bytes.len() != length as usize {
+		return Err(io::ErrorKind::UnexpectedEof.into());
+	}
+	Ok(bytes)
+}
+
+fn read_text<R: Read>(r: &mut R) -> Result<String, DocumentError> {
+	let length = u32::from_le_bytes(read_bytes(r)?);
+	String::from_utf8(read_vec(r, length)?)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e).into())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Height, Parsable, Prefab};
+
+	fn entry(name: &str, height: i8) -> PackEntry {
+		let mut document = PatternDocument::new(Map::default());
+		document.map.heights.0[1][1] = Height(height);
+		document.map.prefabs.0[1][1] = Prefab::Melee;
+		document.metadata.title = format!("Arena {}", name);
+		PackEntry {
+			name: name.to_string(),
+			document,
+			thumbnail: None,
+		}
+	}
+
+	#[test]
+	fn add_and_remove() {
+		let mut pack = Pack::new();
+		assert_eq!(pack.add(entry("a", 1)).unwrap(), None);
+		assert_eq!(pack.add(entry("b", 2)).unwrap(), None);
+		assert_eq!(pack.add(entry("a", 3)).unwrap(), Some(entry("a", 1)));
+		assert_eq!(pack.entries().len(), 2);
+		assert_eq!(pack.get("a"), Some(&entry("a", 3)));
+		assert_eq!(pack.remove("a"), Some(entry("a", 3)));
+		assert_eq!(pack.remove("a"), None);
+		for name in ["", "..", "a/b", "a\\b"].iter() {
+			assert!(matches!(
+				pack.add(entry(name, 0)),
+				Err(PackError::InvalidName(_))
+			));
+		}
+		let long = "a".repeat(u16::MAX as usize);
+		assert!(pack.add(entry(&long, 0)).is_ok());
+		assert!(matches!(
+			pack.add(entry(&format!("{}a", long), 0)),
+			Err(PackError::InvalidName(_))
+		));
+		let mut bytes = Vec::new();
+		pack.write(&mut bytes).unwrap();
+		assert_eq!(Pack::read(&bytes[..]).unwrap(), pack);
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut pack = Pack::new();
+		pack.add(entry("first", -15)).unwrap();
+		let mut second = entry("second", 20);
+		second.thumbnail = Some(vec![1, 2, 3]);
+		pack.add(second).unwrap();
+
+		let mut bytes = Vec::new();
+		pack.write(&mut bytes).unwrap();
+		assert!(bytes.starts_with(b"CGPACK\x01\x02\0\0\0"));
+		assert_eq!(Pack::read(&bytes[..]).unwrap(), pack);
+
+		assert!(matches!(
+			Pack::read(&bytes[..bytes.len() - 1]),
+			Err(PackError::Io(_))
+		));
+		assert!(matches!(
+			Pack::read(&b"PK\x03\x04"[..]),
+			Err(PackError::NotAPack)
+		));
+		bytes[6] = 9;
+		assert!(matches!(Pack::read(&bytes[..]), Err(PackError::Version(9))));
+	}
+
+	#[test]
+	fn extract() {
+		let dir = std::env::temp_dir()
+			.join(format!("cybergrind-pack-{}", std::process::id()));
+		let mut pack = Pack::new();
+		let mut first = entry("first", 4);
+		first.thumbnail = Some(vec![1, 2, 3]);
+		pack.add(first).unwrap();
+		pack.extract(&dir).unwrap();
+
+		let document = PatternDocument::read(&dir.join("first.cgp")).unwrap();
+		assert_eq!(document, entry("first", 4).document);
+		assert_eq!(
+			fs::read(dir.join("first.thumbnail.png")).unwrap(),
+			[1, 2, 3]
+		);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
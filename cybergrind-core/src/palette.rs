@@ -0,0 +1,53 @@
+//! Colors shared by the renderers.
+
+use crate::{Height, Map};
+
+pub(crate) const PIT_COLOR: [u8; 3] = [25, 25, 30];
+/// Blue through green and yellow to red, lowest to highest.
+pub(crate) const RAMP: [[u8; 3]; 4] =
+	[[40, 70, 170], [60, 170, 90], [230, 210, 90], [200, 70, 50]];
+
+/// Picks a color for `height`, spreading the non-pit heights of the map
+/// between `low` and `high` over the whole ramp.
+pub(crate) fn height_color(height: Height, low: i8, high: i8) -> [u8; 3] {
+	if height.is_pit() {
+		return PIT_COLOR;
+	}
+	let span = (high as f32 - low as f32).max(1.0);
+	let t = ((height.0 as f32 - low as f32) / span).clamp(0.0, 1.0)
+		* (RAMP.len() - 1) as f32;
+	let i = (t as usize).min(RAMP.len() - 2);
+	let f = t - i as f32;
+	let mut color = [0; 3];
+	for c in 0..3 {
+		color[c] =
+			(RAMP[i][c] as f32 * (1.0 - f) + RAMP[i + 1][c] as f32 * f) as u8;
+	}
+	color
+}
+
+/// The lowest and highest heights of the map that aren't pits.
+pub(crate) fn ground_range(map: &Map) -> (i8, i8) {
+	let ground: Vec<i8> = map
+		.heights
+		.iter()
+		.filter(|(_, h)| !h.is_pit())
+		.map(|(_, h)| h.0)
+		.collect();
+	let low = ground.iter().copied().min().unwrap_or(0);
+	let high = ground.iter().copied().max().unwrap_or(0);
+	(low, high)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn colors() {
+		assert_eq!(height_color(Height(-15), 0, 4), PIT_COLOR);
+		assert_eq!(height_color(Height(0), 0, 4), RAMP[0]);
+		assert_eq!(height_color(Height(4), 0, 4), RAMP[3]);
+		assert_eq!(height_color(Height(2), 2, 2), RAMP[0]);
+	}
+}
@@ -0,0 +1,402 @@
+use std::fmt;
+
+use nom::{
+	error::{ErrorKind, FromExternalError},
+	Offset,
+};
+
+use crate::{Grid, Map, Parsable};
+
+/// The part of a pattern file a [`ParseError`] was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+	Heights,
+	Prefabs,
+}
+
+impl fmt::Display for Section {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Section::Heights => write!(f, "heights"),
+			Section::Prefabs => write!(f, "prefabs"),
+		}
+	}
+}
+
+/// What the parser was looking for when it gave up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+	/// A single digit or a parenthesized number.
+	Height,
+	/// The number inside a `(..)` height.
+	HeightNumber,
+	CloseParen,
+	Prefab,
+	LineEnding,
+	/// The empty line between the heights and the prefabs.
+	BlankLine,
+}
+
+impl fmt::Display for Expected {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Expected::Height => write!(f, "a height digit or `(`"),
+			Expected::HeightNumber => write!(f, "a number"),
+			Expected::CloseParen => write!(f, "`)`"),
+			Expected::Prefab => write!(f, "a prefab character (a letter or digit)"),
+			Expected::LineEnding => write!(f, "end of line"),
+			Expected::BlankLine => write!(f, "a blank line"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+	/// A parser failed without saying what it wanted.
+	Unexpected,
+	Expected(Expected),
+	/// A parenthesized height outside of [`Height::MIN`]..=[`Height::MAX`].
+	///
+	/// [`Height::MIN`]: crate::Height::MIN
+	/// [`Height::MAX`]: crate::Height::MAX
+	HeightOutOfRange(String),
+	/// A row with the wrong number of tiles in it.
+	RowLength(usize),
+	/// A grid with the wrong number of rows in it.
+	RowCount(usize),
+	/// A UTF-8 byte order mark when [`ParseOptions::allow_bom`] is off.
+	ByteOrderMark,
+	/// A `\r\n` line ending when [`ParseOptions::allow_crlf`] is off.
+	CarriageReturn,
+	/// Text after the prefab grid when
+	/// [`ParseOptions::allow_trailing_content`] is off.
+	TrailingContent,
+}
+
+/// How forgiving [`Map::from_str_with`] is about the text around the grids.
+///
+/// [`Map::from_str_with`]: crate::Map::from_str_with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+	/// Skip a leading UTF-8 byte order mark.
+	pub allow_bom: bool,
+	/// Accept `\r\n` line endings as well as `\n`.
+	pub allow_crlf: bool,
+	/// Ignore whitespace before and after the grids, like a trailing newline.
+	pub allow_surrounding_whitespace: bool,
+	/// Ignore anything at all after the prefab grid.
+	pub allow_trailing_content: bool,
+}
+
+impl ParseOptions {
+	/// Only accepts exactly what `Map::to_string` produces.
+	pub const fn strict() -> Self {
+		Self {
+			allow_bom: false,
+			allow_crlf: false,
+			allow_surrounding_whitespace: false,
+			allow_trailing_content: false,
+		}
+	}
+
+	/// Accepts the encodings editors tend to save files with, but still
+	/// rejects leftover content after the prefabs.
+	pub const fn lenient() -> Self {
+		Self {
+			allow_bom: true,
+			allow_crlf: true,
+			allow_surrounding_whitespace: true,
+			allow_trailing_content: false,
+		}
+	}
+}
+
+impl Default for ParseOptions {
+	fn default() -> Self {
+		Self::lenient()
+	}
+}
+
+/// Error type threaded through the nom parsers of [`Parsable`] types.
+///
+/// [`Parsable`]: crate::Parsable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenError<'a> {
+	pub input: &'a str,
+	pub kind: ParseErrorKind,
+}
+
+impl<'a> nom::error::ParseError<&'a str> for TokenError<'a> {
+	fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+		Self {
+			input,
+			kind: ParseErrorKind::Unexpected,
+		}
+	}
+
+	fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+		other
+	}
+
+	fn or(self, other: Self) -> Self {
+		// keep whichever branch got further into the input
+		if other.input.len() < self.input.len() {
+			other
+		} else {
+			self
+		}
+	}
+}
+
+/// The only external errors come from reading the number in a height.
+impl<'a, E> FromExternalError<&'a str, E> for TokenError<'a> {
+	fn from_external_error(input: &'a str, _: ErrorKind, _: E) -> Self {
+		let number = input
+			.find(|c: char| c != '-' && !c.is_ascii_digit())
+			.map_or(input, |end| &input[..end]);
+		Self {
+			input,
+			kind: ParseErrorKind::HeightOutOfRange(number.to_string()),
+		}
+	}
+}
+
+pub type TokenResult<'a, O> = nom::IResult<&'a str, O, TokenError<'a>>;
+
+/// Replaces an uninformative error raised right where `f` started with one
+/// saying it expected `expected`.
+pub fn expect<'a, O, F>(
+	expected: Expected,
+	mut f: F,
+) -> impl FnMut(&'a str) -> TokenResult<'a, O>
+where
+	F: nom::Parser<&'a str, O, TokenError<'a>>,
+{
+	move |input: &'a str| {
+		f.parse(input).map_err(|err| {
+			err.map(|e| {
+				if e.kind == ParseErrorKind::Unexpected && e.input == input {
+					TokenError {
+						input,
+						kind: ParseErrorKind::Expected(expected),
+					}
+				} else {
+					e
+				}
+			})
+		})
+	}
+}
+
+/// A failure to parse a pattern file, positioned in the original text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	pub section: Section,
+	/// 1-based line number.
+	pub line: usize,
+	/// 1-based column, counted in characters.
+	pub column: usize,
+	pub kind: ParseErrorKind,
+	/// The character the parser stopped at, `None` at the end of input.
+	pub found: Option<char>,
+}
+
+impl ParseError {
+	pub(crate) fn at(
+		source: &str,
+		rest: &str,
+		section: Section,
+		kind: ParseErrorKind,
+	) -> Self {
+		let consumed = &source[..source.offset(rest)];
+		let line_start = consumed.rfind('\n').map_or(0, |i| i + 1);
+		Self {
+			section,
+			line: consumed.matches('\n').count() + 1,
+			column: consumed[line_start..].chars().count() + 1,
+			kind,
+			found: rest.chars().next(),
+		}
+	}
+
+	pub(crate) fn from_nom(
+		source: &str,
+		section: Section,
+		err: nom::Err<TokenError>,
+	) -> Self {
+		match err {
+			nom::Err::Error(e) | nom::Err::Failure(e) => {
+				Self::at(source, e.input, section, e.kind)
+			}
+			nom::Err::Incomplete(_) => Self::at(
+				source,
+				&source[source.len()..],
+				section,
+				ParseErrorKind::Unexpected,
+			),
+		}
+	}
+}
+
+fn describe(found: Option<char>) -> String {
+	match found {
+		None => "end of input".to_string(),
+		Some('\n') | Some('\r') => "end of line".to_string(),
+		Some(c) => format!("`{}`", c.escape_debug()),
+	}
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{} ({}): ", self.line, self.column, self.section)?;
+		match &self.kind {
+			ParseErrorKind::Unexpected => {
+				write!(f, "unexpected {}", describe(self.found))
+			}
+			ParseErrorKind::Expected(expected) => {
+				write!(f, "expected {}, found {}", expected, describe(self.found))
+			}
+			ParseErrorKind::HeightOutOfRange(number) => {
+				write!(f, "height {} is out of range", number)
+			}
+			ParseErrorKind::RowLength(len) => {
+				write!(f, "expected 16 tiles in this row, found {}", len)
+			}
+			ParseErrorKind::RowCount(count) => {
+				write!(f, "expected 16 rows, found {}", count)
+			}
+			ParseErrorKind::ByteOrderMark => write!(f, "unexpected byte order mark"),
+			ParseErrorKind::CarriageReturn => {
+				write!(f, "unexpected carriage return, expected `\\n` line endings")
+			}
+			ParseErrorKind::TrailingContent => {
+				write!(f, "unexpected {} after the prefabs", describe(self.found))
+			}
+		}
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+/// Skips past the token at the start of `input` that failed to parse.
+fn skip_token(input: &str) -> &str {
+	if input.starts_with('(') {
+		input.find(')').map_or("", |end| &input[end + 1..])
+	} else {
+		let mut chars = input.chars();
+		chars.next();
+		chars.as_str()
+	}
+}
+
+fn recover_row<T: Parsable + Copy>(
+	source: &str,
+	line: &str,
+	section: Section,
+	errors: &mut Vec<ParseError>,
+) -> [T; 16] {
+	let mut row = [T::default(); 16];
+	let mut len = 0;
+	let mut rest = line;
+	while !rest.is_empty() {
+		match T::parse(rest) {
+			Ok((r, v)) => {
+				if let Some(item) = row.get_mut(len) {
+					*item = v;
+				}
+				rest = r;
+			}
+			Err(e) => {
+				errors.push(ParseError::from_nom(source, section, e));
+				rest = skip_token(rest);
+			}
+		}
+		len += 1;
+	}
+	if len != 16 {
+		errors.push(ParseError::at(
+			source,
+			line,
+			section,
+			ParseErrorKind::RowLength(len),
+		));
+	}
+	row
+}
+
+fn recover_grid<T: Parsable + Copy>(
+	source: &str,
+	lines: &[&str],
+	section: Section,
+	errors: &mut Vec<ParseError>,
+) -> Grid<T> {
+	let mut grid = Grid::<T>::default();
+	for (row, line) in grid.0.iter_mut().zip(lines) {
+		*row = recover_row(source, line, section, errors);
+	}
+	if lines.len() != 16 {
+		let at = lines
+			.get(16)
+			.or_else(|| lines.last())
+			.copied()
+			.unwrap_or(&source[source.len()..]);
+		errors.push(ParseError::at(
+			source,
+			at,
+			section,
+			ParseErrorKind::RowCount(lines.len()),
+		));
+	}
+	grid
+}
+
+/// Parses `source` line by line, skipping over anything malformed instead of
+/// stopping at it. Tiles that couldn't be read are left at their default. A
+/// byte order mark is skipped, as by [`ParseOptions::lenient`].
+pub(crate) fn recover_map(source: &str) -> (Map, Vec<ParseError>) {
+	let mut errors = Vec::new();
+	let body = source.strip_prefix('\u{feff}').unwrap_or(source);
+	let mut lines: Vec<&str> = body
+		.split('\n')
+		.map(|line| line.strip_suffix('\r').unwrap_or(line))
+		.collect();
+	while lines.len() > 1 && lines.last() == Some(&"") {
+		lines.pop();
+	}
+
+	let (heights, prefabs) = match lines.iter().position(|l| l.is_empty()) {
+		Some(blank) => {
+			let mut prefabs = &lines[blank + 1..];
+			while let Some((extra, rest)) = prefabs.split_first() {
+				if !extra.is_empty() {
+					break;
+				}
+				errors.push(ParseError::at(
+					source,
+					extra,
+					Section::Prefabs,
+					ParseErrorKind::Expected(Expected::Prefab),
+				));
+				prefabs = rest;
+			}
+			(&lines[..blank], prefabs)
+		}
+		None => {
+			let split = lines.len().min(16);
+			let at = lines.get(split).copied().unwrap_or(&source[source.len()..]);
+			errors.push(ParseError::at(
+				source,
+				at,
+				Section::Heights,
+				ParseErrorKind::Expected(Expected::BlankLine),
+			));
+			lines.split_at(split)
+		}
+	};
+
+	let map = Map {
+		heights: recover_grid(source, heights, Section::Heights, &mut errors),
+		prefabs: recover_grid(source, prefabs, Section::Prefabs, &mut errors),
+	};
+	(map, errors)
+}
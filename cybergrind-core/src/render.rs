@@ -0,0 +1,417 @@
+//! Drawing patterns as images without a GPU, for thumbnails.
+
+use std::io::Write;
+
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use crate::{
+	palette::{ground_range, height_color},
+	ImageError, Map, Prefab, PREFAB_ATLAS,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+	/// Looking straight down, with slopes shaded.
+	TopDown,
+	/// Pillars seen from above one corner of the arena.
+	Isometric,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+	pub view: View,
+	/// Width in pixels of each tile. In the isometric view this is the width
+	/// of a tile's top face. A size of 0 is treated as 1, and sizes above
+	/// [`RenderOptions::MAX_TILE_SIZE`] as that.
+	pub tile_size: u32,
+	/// Draw prefab icons on their tiles.
+	pub prefabs: bool,
+}
+
+impl RenderOptions {
+	/// The largest tile size drawn, which keeps an isometric render of the
+	/// tallest map under a few hundred megabytes.
+	pub const MAX_TILE_SIZE: u32 = 256;
+
+	fn tile_size(&self) -> u32 {
+		self.tile_size.clamp(1, Self::MAX_TILE_SIZE)
+	}
+}
+
+impl Default for RenderOptions {
+	fn default() -> Self {
+		Self {
+			view: View::TopDown,
+			tile_size: 16,
+			prefabs: true,
+		}
+	}
+}
+
+/// An RGBA image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+	pub width: u32,
+	pub height: u32,
+	/// Row by row, from the top left.
+	pub pixels: Vec<[u8; 4]>,
+}
+
+impl Canvas {
+	/// A transparent canvas.
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			pixels: vec![[0; 4]; width as usize * height as usize],
+		}
+	}
+
+	fn index(&self, x: u32, y: u32) -> usize {
+		y as usize * self.width as usize + x as usize
+	}
+
+	pub fn get(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+		if x < self.width && y < self.height {
+			Some(self.pixels[self.index(x, y)])
+		} else {
+			None
+		}
+	}
+
+	/// Draws `color` over the pixel at `(x, y)`, blending by its alpha.
+	/// Pixels off the canvas are ignored.
+	fn blend(&mut self, x: i32, y: i32, color: [u8; 4]) {
+		if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+			return;
+		}
+		let index = self.index(x as u32, y as u32);
+		let pixel = &mut self.pixels[index];
+		let alpha = color[3] as u32;
+		for i in 0..3 {
+			pixel[i] = ((color[i] as u32 * alpha + pixel[i] as u32 * (255 - alpha))
+				/ 255) as u8;
+		}
+		pixel[3] = (alpha + pixel[3] as u32 * (255 - alpha) / 255) as u8;
+	}
+
+	fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: [u8; 4]) {
+		for py in y..y + h {
+			for px in x..x + w {
+				self.blend(px, py, color);
+			}
+		}
+	}
+
+	/// Fills a convex polygon, taking the pixels whose centers are inside it.
+	fn fill_polygon(&mut self, points: &[(f32, f32)], color: [u8; 4]) {
+		let min_x = points.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+		let max_x = points.iter().map(|p| p.0).fold(f32::NEG_INFINITY, f32::max);
+		let min_y = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+		let max_y = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+		for y in min_y.floor() as i32..max_y.ceil() as i32 {
+			for x in min_x.floor() as i32..max_x.ceil() as i32 {
+				let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+				let sides = points
+					.iter()
+					.zip(points.iter().cycle().skip(1))
+					.map(|(a, b)| (b.0 - a.0) * (cy - a.1) - (b.1 - a.1) * (cx - a.0));
+				let (mut left, mut right) = (false, false);
+				for side in sides {
+					left |= side < 0.0;
+					right |= side > 0.0;
+				}
+				if !(left && right) {
+					self.blend(x, y, color);
+				}
+			}
+		}
+	}
+
+	/// Draws `sprite` scaled to `size` pixels square with its top left at
+	/// `(x, y)`.
+	fn draw_sprite(&mut self, sprite: &Canvas, x: i32, y: i32, size: u32) {
+		for sy in 0..size {
+			for sx in 0..size {
+				let color = sprite
+					.get(sx * sprite.width / size, sy * sprite.height / size)
+					.unwrap();
+				self.blend(x + sx as i32, y + sy as i32, color);
+			}
+		}
+	}
+
+	/// Cuts out the part of the canvas from `y` down, `height` pixels tall.
+	fn rows(&self, y: u32, height: u32) -> Canvas {
+		let start = self.index(0, y);
+		let end = start + height as usize * self.width as usize;
+		Canvas {
+			width: self.width,
+			height,
+			pixels: self.pixels[start..end].to_vec(),
+		}
+	}
+
+	pub fn read_png(bytes: &[u8]) -> Result<Self, ImageError> {
+		let mut decoder = Decoder::new(bytes);
+		decoder
+			.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+		let (info, mut reader) = decoder.read_info()?;
+		let mut buf = vec![0; info.buffer_size()];
+		reader.next_frame(&mut buf)?;
+		let channels = info.color_type.samples();
+		let pixels = buf
+			.chunks(channels)
+			.map(|p| match info.color_type {
+				ColorType::Grayscale => [p[0], p[0], p[0], 255],
+				ColorType::GrayscaleAlpha => [p[0], p[0], p[0], p[1]],
+				ColorType::RGB => [p[0], p[1], p[2], 255],
+				_ => [p[0], p[1], p[2], p[3]],
+			})
+			.collect();
+		Ok(Self {
+			width: info.width,
+			height: info.height,
+			pixels,
+		})
+	}
+
+	pub fn write_png<W: Write>(&self, w: W) -> Result<(), ImageError> {
+		let mut encoder = Encoder::new(w, self.width, self.height);
+		encoder.set_color(ColorType::RGBA);
+		encoder.set_depth(BitDepth::Eight);
+		let data: Vec<u8> = self.pixels.iter().flatten().copied().collect();
+		encoder.write_header()?.write_image_data(&data)?;
+		Ok(())
+	}
+}
+
+/// The editor's icon for a prefab, or `None` for empty tiles.
+fn prefab_icon(atlas: &Canvas, prefab: Prefab) -> Option<Canvas> {
+	let row = match prefab {
+		Prefab::None => return None,
+		Prefab::Hideous => 0,
+		Prefab::Stairs => 1,
+		Prefab::JumpPad => 2,
+		Prefab::Projectile => 3,
+		Prefab::Melee => 4,
+		Prefab::Unknown(_) => 5,
+	};
+	Some(atlas.rows(row * 16, 16))
+}
+
+fn shade([r, g, b]: [u8; 3], light: f32) -> [u8; 4] {
+	let channel = |c: u8| (c as f32 * light).round().clamp(0.0, 255.0) as u8;
+	[channel(r), channel(g), channel(b), 255]
+}
+
+fn render_top_down(
+	map: &Map,
+	options: &RenderOptions,
+	atlas: &Canvas,
+) -> Canvas {
+	let size = options.tile_size() as i32;
+	let mut canvas = Canvas::new(16 * size as u32, 16 * size as u32);
+	let (low, high) = ground_range(map);
+	let height = |x: usize, y: usize| map.heights.0[y][x].0 as f32;
+
+	for ((x, y), h) in map.heights.iter() {
+		// Lit from the top left, so tiles higher than their neighbours
+		// that way are brighter.
+		let left = if x > 0 { height(x - 1, y) } else { h.0 as f32 };
+		let up = if y > 0 { height(x, y - 1) } else { h.0 as f32 };
+		let slope = (h.0 as f32 - left) + (h.0 as f32 - up);
+		let light = (1.0 + slope * 0.05).clamp(0.6, 1.3);
+		let color = height_color(*h, low, high);
+		let (px, py) = (x as i32 * size, y as i32 * size);
+		canvas.fill_rect(px, py, size, size, shade(color, light));
+		if size >= 8 {
+			let edge = shade(color, light * 0.8);
+			canvas.fill_rect(px, py, size, 1, edge);
+			canvas.fill_rect(px, py, 1, size, edge);
+		}
+		if options.prefabs {
+			if let Some(icon) = prefab_icon(atlas, map.prefabs.0[y][x]) {
+				canvas.draw_sprite(&icon, px, py, size as u32);
+			}
+		}
+	}
+	canvas
+}
+
+fn render_isometric(
+	map: &Map,
+	options: &RenderOptions,
+	atlas: &Canvas,
+) -> Canvas {
+	// Each tile's top is a diamond `size` wide and half as tall, and a unit
+	// of height raises it by a quarter of its width.
+	let size = options.tile_size() as f32;
+	let rise = size / 4.0;
+	let (low, high) = ground_range(map);
+	let lowest = map.heights.iter().map(|(_, h)| h.0).min().unwrap();
+	let base = lowest as f32 - 1.0;
+
+	let top = high.max(lowest) as f32;
+	let width = (16.0 * size).ceil() as u32;
+	let height = (8.0 * size + (top - base) * rise).ceil() as u32;
+	let mut canvas = Canvas::new(width, height);
+	let project = |x: f32, y: f32, z: f32| {
+		(
+			8.0 * size + (x - y) * size / 2.0,
+			(x + y) * size / 4.0 + (top - z) * rise,
+		)
+	};
+
+	// Back to front, so nearer pillars cover farther ones.
+	let mut tiles: Vec<(usize, usize)> =
+		map.heights.iter().map(|(tile, _)| tile).collect();
+	tiles.sort_by_key(|(x, y)| x + y);
+	for (x, y) in tiles {
+		let h = map.heights.0[y][x];
+		let color = height_color(h, low, high);
+		let (x0, y0, x1, y1) = (x as f32, y as f32, x as f32 + 1.0, y as f32 + 1.0);
+		let z = h.0 as f32;
+		canvas.fill_polygon(
+			&[
+				project(x0, y1, z),
+				project(x1, y1, z),
+				project(x1, y1, base),
+				project(x0, y1, base),
+			],
+			shade(color, 0.65),
+		);
+		canvas.fill_polygon(
+			&[
+				project(x1, y0, z),
+				project(x1, y1, z),
+				project(x1, y1, base),
+				project(x1, y0, base),
+			],
+			shade(color, 0.8),
+		);
+		canvas.fill_polygon(
+			&[
+				project(x0, y0, z),
+				project(x1, y0, z),
+				project(x1, y1, z),
+				project(x0, y1, z),
+			],
+			shade(color, 1.0),
+		);
+		if options.prefabs {
+			if let Some(icon) = prefab_icon(atlas, map.prefabs.0[y][x]) {
+				let icon_size = (size / 2.0).max(1.0);
+				let (cx, cy) = project(x0 + 0.5, y0 + 0.5, z);
+				canvas.draw_sprite(
+					&icon,
+					(cx - icon_size / 2.0) as i32,
+					(cy - icon_size) as i32,
+					icon_size as u32,
+				);
+			}
+		}
+	}
+	canvas
+}
+
+/// Draws `map` from above, either straight down or at an angle.
+pub fn render(map: &Map, options: &RenderOptions) -> Canvas {
+	let atlas =
+		Canvas::read_png(PREFAB_ATLAS).expect("prefab icons should decode");
+	match options.view {
+		View::TopDown => render_top_down(map, options, &atlas),
+		View::Isometric => render_isometric(map, options, &atlas),
+	}
+}
+
+impl Map {
+	pub fn render(&self, options: &RenderOptions) -> Canvas {
+		render(self, options)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{palette::PIT_COLOR, Height, Parsable, UnknownChar};
+
+	fn sample() -> Map {
+		let mut map = Map::default();
+		map.heights.0[0][1] = Height(-15);
+		map.heights.0[5][5] = Height(4);
+		map.prefabs.0[2][2] = Prefab::JumpPad;
+		map
+	}
+
+	#[test]
+	fn atlas() {
+		let atlas = Canvas::read_png(PREFAB_ATLAS).unwrap();
+		assert_eq!((atlas.width, atlas.height), (16, 96));
+		assert_eq!(prefab_icon(&atlas, Prefab::None), None);
+		assert_eq!(prefab_icon(&atlas, Prefab::Melee).unwrap().height, 16);
+		assert_eq!(
+			prefab_icon(&atlas, Prefab::Unknown(UnknownChar('x')))
+				.unwrap()
+				.height,
+			16
+		);
+	}
+
+	#[test]
+	fn top_down() {
+		let map = sample();
+		let canvas = map.render(&RenderOptions::default());
+		assert_eq!((canvas.width, canvas.height), (256, 256));
+		assert!(canvas.pixels.iter().all(|p| p[3] == 255));
+
+		let center = |canvas: &Canvas, x: u32, y: u32| {
+			canvas.get(x * 16 + 8, y * 16 + 8).unwrap()
+		};
+		// In shadow, being lower than the tile to its left.
+		assert_eq!(center(&canvas, 1, 0), shade(PIT_COLOR, 0.6));
+		assert_eq!(center(&canvas, 3, 3), center(&canvas, 10, 10));
+		assert_ne!(center(&canvas, 5, 5), center(&canvas, 10, 10));
+
+		let plain = map.render(&RenderOptions {
+			prefabs: false,
+			..Default::default()
+		});
+		let icon = (0..16 * 16).any(|i| {
+			canvas.get(32 + i % 16, 32 + i / 16)
+				!= plain.get(32 + i % 16, 32 + i / 16)
+		});
+		assert!(icon);
+	}
+
+	#[test]
+	fn isometric() {
+		let map = sample();
+		let canvas = map.render(&RenderOptions {
+			view: View::Isometric,
+			..Default::default()
+		});
+		// Pillars go from a tile under the pit up to the highest tile.
+		assert_eq!(canvas.width, 256);
+		assert_eq!(canvas.height, 128 + (4 + 16) * 4);
+		// The corners outside the arena are left transparent.
+		assert_eq!(canvas.get(0, 0), Some([0; 4]));
+		// The top of the nearest pillar is flat ground.
+		let near = canvas.get(128, 128 + 4 * 4 - 4).unwrap();
+		assert_eq!(&near[..3], &height_color(Height(0), 0, 4));
+	}
+
+	#[test]
+	fn tile_size_limit() {
+		let canvas = Map::default().render(&RenderOptions {
+			tile_size: u32::MAX,
+			prefabs: false,
+			..Default::default()
+		});
+		assert_eq!(canvas.width, 16 * RenderOptions::MAX_TILE_SIZE);
+		assert_eq!(
+			canvas.get(canvas.width - 1, canvas.height - 1).unwrap()[3],
+			255
+		);
+	}
+}
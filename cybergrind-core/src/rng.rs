@@ -0,0 +1,53 @@
+/// A small seeded random number generator (SplitMix64), so the same seed
+/// gives the same patterns everywhere.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+	pub fn new(seed: u64) -> Self {
+		Self(seed)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	/// A number in `0.0..1.0`.
+	pub fn next_f32(&mut self) -> f32 {
+		(self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+	}
+
+	/// A number in `0..n`. `n` can't be zero.
+	pub fn below(&mut self, n: usize) -> usize {
+		(self.next_u64() % n as u64) as usize
+	}
+
+	pub fn shuffle<T>(&mut self, items: &mut [T]) {
+		for i in (1..items.len()).rev() {
+			items.swap(i, self.below(i + 1));
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn seeded() {
+		// first outputs of the reference SplitMix64 for seed 0
+		let mut rng = Rng::new(0);
+		assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+		assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+		let mut rng = Rng::new(7);
+		for _ in 0..1000 {
+			assert!((0.0..1.0).contains(&rng.next_f32()));
+			assert!(rng.below(5) < 5);
+		}
+	}
+}
@@ -0,0 +1,326 @@
+//! Share codes: whole patterns as one short line of text that survives being
+//! pasted into chat.
+//!
+//! A code is URL-safe base64 without padding, of:
+//!
+//! - a version byte,
+//! - the lowest height as an `i8` and the number of bits per height,
+//! - every height minus the lowest, packed in that many bits,
+//! - every prefab packed in 3 bits, its index in [`Prefab::ALL`]. Unknown
+//!   prefabs are the escape code 7 followed by their character as a byte,
+//! - a Fletcher-16 checksum of everything before it.
+//!
+//! Tiles are in row order and bits are packed most significant first.
+
+use std::{convert::TryFrom, fmt};
+
+use crate::{Grid, Height, Map, Parsable, Prefab};
+
+const VERSION: u8 = 1;
+const PREFAB_BITS: u32 = 3;
+const UNKNOWN_PREFAB: u32 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareCodeError {
+	/// A character that isn't URL-safe base64.
+	InvalidCharacter(char),
+	/// The code was cut short or has something extra on the end.
+	Length,
+	Checksum,
+	/// The code was made by a newer version of this library.
+	Version(u8),
+	/// A prefab number that doesn't stand for any prefab.
+	Prefab(u8),
+	/// A height outside of [`Height::MIN`]..=[`Height::MAX`].
+	Height(i32),
+}
+
+impl fmt::Display for ShareCodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ShareCodeError::InvalidCharacter(c) => {
+				write!(f, "invalid character {:?} in share code", c)
+			}
+			ShareCodeError::Length => write!(f, "share code is incomplete"),
+			ShareCodeError::Checksum => {
+				write!(f, "share code is damaged, its checksum doesn't match")
+			}
+			ShareCodeError::Version(v) => {
+				write!(f, "share code is version {}, expected {}", v, VERSION)
+			}
+			ShareCodeError::Prefab(p) => {
+				write!(f, "share code has an unknown prefab {}", p)
+			}
+			ShareCodeError::Height(h) => {
+				write!(f, "share code has an out of range height {}", h)
+			}
+		}
+	}
+}
+
+impl std::error::Error for ShareCodeError {}
+
+const ALPHABET: &[u8; 64] =
+	b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// URL-safe base64 without padding.
+fn encode_base64(bytes: &[u8]) -> String {
+	let mut out = String::with_capacity(bytes.len() * 4 / 3 + 1);
+	for chunk in bytes.chunks(3) {
+		let n = chunk
+			.iter()
+			.enumerate()
+			.fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+		for i in 0..=chunk.len() {
+			out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+		}
+	}
+	out
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>, ShareCodeError> {
+	let mut out = Vec::with_capacity(s.len() * 3 / 4);
+	let (mut n, mut bits) = (0u32, 0);
+	for c in s.chars() {
+		let value = ALPHABET
+			.iter()
+			.position(|a| *a as char == c)
+			.ok_or(ShareCodeError::InvalidCharacter(c))?;
+		n = n << 6 | value as u32;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((n >> bits) as u8);
+			n &= (1 << bits) - 1;
+		}
+	}
+	// Leftover bits are padding and have to be zero, or the code has a
+	// character too many.
+	if bits >= 6 || n != 0 {
+		return Err(ShareCodeError::Length);
+	}
+	Ok(out)
+}
+
+fn fletcher16(bytes: &[u8]) -> [u8; 2] {
+	let (mut a, mut b) = (0u16, 0u16);
+	for byte in bytes {
+		a = (a + *byte as u16) % 255;
+		b = (b + a) % 255;
+	}
+	[b as u8, a as u8]
+}
+
+#[derive(Default)]
+struct BitWriter {
+	bytes: Vec<u8>,
+	used: u32,
+}
+
+impl BitWriter {
+	fn write(&mut self, value: u32, bits: u32) {
+		for i in (0..bits).rev() {
+			if self.used as usize == self.bytes.len() * 8 {
+				self.bytes.push(0);
+			}
+			let bit = (value >> i & 1) as u8;
+			*self.bytes.last_mut().unwrap() |= bit << (7 - self.used % 8);
+			self.used += 1;
+		}
+	}
+}
+
+struct BitReader<'a> {
+	bytes: &'a [u8],
+	used: usize,
+}
+
+impl BitReader<'_> {
+	fn read(&mut self, bits: u32) -> Result<u32, ShareCodeError> {
+		let mut value = 0;
+		for _ in 0..bits {
+			let byte = self
+				.bytes
+				.get(self.used / 8)
+				.ok_or(ShareCodeError::Length)?;
+			value = value << 1 | (byte >> (7 - self.used % 8) & 1) as u32;
+			self.used += 1;
+		}
+		Ok(value)
+	}
+}
+
+impl Map {
+	pub fn to_share_code(&self) -> String {
+		let low = self.heights.iter().map(|(_, h)| h.0).min().unwrap();
+		let high = self.heights.iter().map(|(_, h)| h.0).max().unwrap();
+		let span = (high as i32 - low as i32) as u32;
+		let bits = 32 - span.leading_zeros();
+
+		let mut writer = BitWriter::default();
+		writer.write(VERSION as u32, 8);
+		writer.write(low as u8 as u32, 8);
+		writer.write(bits, 8);
+		for (_, h) in self.heights.iter() {
+			writer.write((h.0 as i32 - low as i32) as u32, bits);
+		}
+		for (_, p) in self.prefabs.iter() {
+			match p {
+				Prefab::Unknown(c) => {
+					writer.write(UNKNOWN_PREFAB, PREFAB_BITS);
+					writer.write(c.get() as u32, 8);
+				}
+				p => {
+					let code = Prefab::ALL.iter().position(|a| a == p).unwrap();
+					writer.write(code as u32, PREFAB_BITS);
+				}
+			}
+		}
+		let mut bytes = writer.bytes;
+		let checksum = fletcher16(&bytes);
+		bytes.extend_from_slice(&checksum);
+		encode_base64(&bytes)
+	}
+
+	/// Reads a code from [`Map::to_share_code`], ignoring any whitespace
+	/// around it.
+	pub fn from_share_code(code: &str) -> Result<Self, ShareCodeError> {
+		let bytes = decode_base64(code.trim())?;
+		if bytes.len() < 2 {
+			return Err(ShareCodeError::Length);
+		}
+		let (data, checksum) = bytes.split_at(bytes.len() - 2);
+		if fletcher16(data) != checksum {
+			return Err(ShareCodeError::Checksum);
+		}
+
+		let mut reader = BitReader {
+			bytes: data,
+			used: 0,
+		};
+		let version = reader.read(8)? as u8;
+		if version != VERSION {
+			return Err(ShareCodeError::Version(version));
+		}
+		let low = reader.read(8)? as u8 as i8;
+		let bits = reader.read(8)?;
+		if bits > 8 {
+			return Err(ShareCodeError::Length);
+		}
+		let mut map = Map {
+			heights: Grid::default(),
+			prefabs: Grid::default(),
+		};
+		for row in map.heights.0.iter_mut() {
+			for height in row.iter_mut() {
+				let value = low as i32 + reader.read(bits)? as i32;
+				*height = i8::try_from(value)
+					.ok()
+					.and_then(Height::new)
+					.ok_or(ShareCodeError::Height(value))?;
+			}
+		}
+		for row in map.prefabs.0.iter_mut() {
+			for prefab in row.iter_mut() {
+				let code = reader.read(PREFAB_BITS)? as u8;
+				*prefab = if code as u32 == UNKNOWN_PREFAB {
+					let c = reader.read(8)? as u8 as char;
+					match Prefab::from_char(c) {
+						Some(p @ Prefab::Unknown(_)) => p,
+						_ => return Err(ShareCodeError::Prefab(code)),
+					}
+				} else {
+					*Prefab::ALL
+						.get(code as usize)
+						.ok_or(ShareCodeError::Prefab(code))?
+				};
+			}
+		}
+		// no whole bytes left over
+		if data.len() * 8 - reader.used >= 8 {
+			return Err(ShareCodeError::Length);
+		}
+		Ok(map)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::UnknownChar;
+
+	#[test]
+	fn base64() {
+		for (bytes, text) in [
+			(&b""[..], ""),
+			(b"f", "Zg"),
+			(b"fo", "Zm8"),
+			(b"foo", "Zm9v"),
+			(b"foob", "Zm9vYg"),
+			(&[0xfb, 0xff], "-_8"),
+		]
+		.iter()
+		{
+			assert_eq!(encode_base64(bytes), *text);
+			assert_eq!(decode_base64(text).as_deref(), Ok(*bytes));
+		}
+		assert_eq!(decode_base64("Zm9"), Err(ShareCodeError::Length));
+		assert_eq!(decode_base64("Z"), Err(ShareCodeError::Length));
+		assert_eq!(
+			decode_base64("Zm+v"),
+			Err(ShareCodeError::InvalidCharacter('+'))
+		);
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut map = Map::default();
+		map.heights.0[0][1] = Height(-15);
+		map.heights.0[3][2] = Height::MAX;
+		map.prefabs.0[3][2] = Prefab::JumpPad;
+		map.prefabs.0[15][15] = Prefab::Hideous;
+		map.prefabs.0[7][9] = Prefab::Unknown(UnknownChar('q'));
+		let code = map.to_share_code();
+		assert!(code
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+		assert_eq!(Map::from_share_code(&format!(" {}\n", code)), Ok(map));
+
+		let flat = Map::default().to_share_code();
+		assert!(flat.len() < code.len());
+		assert_eq!(Map::from_share_code(&flat), Ok(Map::default()));
+	}
+
+	#[test]
+	fn damaged() {
+		let mut map = Map::default();
+		map.prefabs.0[4][4] = Prefab::Melee;
+		let code = map.to_share_code();
+
+		let mut changed: Vec<char> = code.chars().collect();
+		changed[10] = if changed[10] == 'A' { 'B' } else { 'A' };
+		let changed: String = changed.into_iter().collect();
+		assert_eq!(
+			Map::from_share_code(&changed),
+			Err(ShareCodeError::Checksum)
+		);
+		assert_eq!(
+			Map::from_share_code(&code[..code.len() - 4]),
+			Err(ShareCodeError::Checksum)
+		);
+		assert_eq!(Map::from_share_code(""), Err(ShareCodeError::Length));
+
+		let mut writer = BitWriter::default();
+		writer.write(VERSION as u32, 8);
+		writer.write(45, 8);
+		writer.write(4, 8);
+		writer.write(15, 4);
+		let mut bytes = writer.bytes;
+		let checksum = fletcher16(&bytes);
+		bytes.extend_from_slice(&checksum);
+		assert_eq!(
+			Map::from_share_code(&encode_base64(&bytes)),
+			Err(ShareCodeError::Height(60))
+		);
+	}
+}
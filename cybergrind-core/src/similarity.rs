@@ -0,0 +1,146 @@
+//! How alike two patterns are, for finding the pattern a remix was made
+//! from.
+
+use crate::{Map, Symmetry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistanceOptions {
+	/// How much one mismatched prefab counts for, against a height
+	/// difference of one.
+	pub prefab_weight: u32,
+	/// Compare against every turned and mirrored copy of the other pattern,
+	/// keeping the closest.
+	pub symmetries: bool,
+}
+
+impl Default for DistanceOptions {
+	fn default() -> Self {
+		Self {
+			prefab_weight: 4,
+			symmetries: true,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Distance {
+	/// The sum of the height differences of every tile.
+	pub heights: u32,
+	/// The number of tiles with different prefabs.
+	pub prefabs: u32,
+	/// The heights and prefabs together, weighted by
+	/// [`DistanceOptions::prefab_weight`]. Lower is more alike. Wider than
+	/// the others so that no weight can overflow it.
+	pub total: u64,
+	/// What was done to the other pattern to line it up with this one.
+	pub symmetry: Symmetry,
+}
+
+impl Map {
+	pub fn distance(&self, other: &Map, options: &DistanceOptions) -> Distance {
+		let symmetries: &[Symmetry] = if options.symmetries {
+			&Symmetry::ALL
+		} else {
+			&[Symmetry::Identity]
+		};
+		symmetries
+			.iter()
+			.map(|symmetry| {
+				let other = other.apply(*symmetry);
+				let heights = self
+					.heights
+					.iter()
+					.zip(other.heights.iter())
+					.map(|((_, a), (_, b))| (a.0 as i32 - b.0 as i32).unsigned_abs())
+					.sum();
+				let prefabs = self
+					.prefabs
+					.iter()
+					.zip(other.prefabs.iter())
+					.filter(|((_, a), (_, b))| a != b)
+					.count() as u32;
+				Distance {
+					heights,
+					prefabs,
+					total: heights as u64 + prefabs as u64 * options.prefab_weight as u64,
+					symmetry: *symmetry,
+				}
+			})
+			.min_by_key(|d| d.total)
+			.unwrap()
+	}
+}
+
+/// Sorts `candidates` from most to least like `target`. Ties keep their
+/// order.
+pub fn rank<K>(
+	target: &Map,
+	candidates: impl IntoIterator<Item = (K, Map)>,
+	options: &DistanceOptions,
+) -> Vec<(K, Distance)> {
+	let mut ranked: Vec<_> = candidates
+		.into_iter()
+		.map(|(key, map)| (key, target.distance(&map, options)))
+		.collect();
+	ranked.sort_by_key(|(_, d)| d.total);
+	ranked
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{test::lopsided, Height, Parsable, Prefab};
+
+	#[test]
+	fn distance() {
+		let map = lopsided();
+		let mut remix = map;
+		remix.heights.0[2][5] = Height(4);
+		remix.prefabs.0[9][9] = Prefab::Melee;
+		let d = map.distance(&remix, &DistanceOptions::default());
+		assert_eq!(
+			d,
+			Distance {
+				heights: 3,
+				prefabs: 1,
+				total: 7,
+				symmetry: Symmetry::Identity
+			}
+		);
+		assert_eq!(map.distance(&map, &DistanceOptions::default()).total, 0);
+		let heavy = DistanceOptions {
+			prefab_weight: u32::MAX,
+			..DistanceOptions::default()
+		};
+		assert_eq!(map.distance(&remix, &heavy).total, 3 + u32::MAX as u64);
+	}
+
+	#[test]
+	fn symmetries() {
+		let map = lopsided();
+		let turned = map.rotate90();
+		let d = map.distance(&turned, &DistanceOptions::default());
+		assert_eq!(d.total, 0);
+		assert_eq!(map, turned.apply(d.symmetry));
+
+		let options = DistanceOptions {
+			symmetries: false,
+			..DistanceOptions::default()
+		};
+		assert_eq!(map.distance(&turned, &options).total, 2 * (15 + 7) + 2 * 4);
+	}
+
+	#[test]
+	fn ranking() {
+		let map = lopsided();
+		let mut near = map.flip_vertical();
+		near.heights.0[8][8] = Height(1);
+		let ranked = rank(
+			&map,
+			vec![("far", Map::default()), ("near", near), ("same", map)],
+			&DistanceOptions::default(),
+		);
+		let order: Vec<_> = ranked.iter().map(|(k, _)| *k).collect();
+		assert_eq!(order, ["same", "near", "far"]);
+	}
+}
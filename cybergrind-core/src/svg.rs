@@ -0,0 +1,264 @@
+//! Drawing patterns as SVG diagrams.
+//!
+//! Everything is drawn in tile units, with the grid from `(0, 0)` to
+//! `(16, 16)`. A red arrow left of the grid, level with the middle of it,
+//! points along +x to show which way the pattern faces.
+
+use std::fmt::Write;
+
+use crate::{
+	palette::{ground_range, height_color},
+	Map, Prefab,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+	/// Width in pixels of each tile when the diagram isn't scaled.
+	pub tile_size: f32,
+	/// Write each tile's height in it.
+	pub numbers: bool,
+	/// Explain the prefab symbols under the grid.
+	pub legend: bool,
+}
+
+impl Default for SvgOptions {
+	fn default() -> Self {
+		Self {
+			tile_size: 32.0,
+			numbers: true,
+			legend: true,
+		}
+	}
+}
+
+/// Room left of the grid for the arrow.
+const LEFT: f32 = 5.0;
+const TOP: f32 = 1.0;
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+	format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Black or white, whichever is easier to read on a tile of this color.
+fn ink([r, g, b]: [u8; 3]) -> &'static str {
+	if r as u32 * 299 + g as u32 * 587 + b as u32 * 114 > 128_000 {
+		"#000"
+	} else {
+		"#fff"
+	}
+}
+
+/// Writes a coordinate with at most three decimals.
+fn num(v: f32) -> String {
+	let s = format!("{:.3}", v);
+	s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// A symbol for `prefab` centered on `(x, y)`, about half a tile across.
+fn symbol(out: &mut String, prefab: Prefab, x: f32, y: f32) {
+	let style = format!(
+		r##"fill="{}" stroke="#000" stroke-width="0.04""##,
+		hex(prefab.color())
+	);
+	let r = 0.22;
+	let points: &[(f32, f32)] = match prefab {
+		Prefab::None => return,
+		Prefab::Melee => {
+			writeln!(
+				out,
+				r#"<circle cx="{}" cy="{}" r="{}" {}/>"#,
+				num(x),
+				num(y),
+				r,
+				style
+			)
+			.unwrap();
+			return;
+		}
+		Prefab::Unknown(_) => {
+			writeln!(
+				out,
+				r#"<circle cx="{}" cy="{}" r="{}" {}/><text x="{}" y="{}" font-size="{}">?</text>"#,
+				num(x),
+				num(y),
+				r,
+				style,
+				num(x),
+				num(y + 0.12),
+				num(1.5 * r)
+			)
+			.unwrap();
+			return;
+		}
+		Prefab::Projectile => &[(-r, -r), (r, -r), (r, r), (-r, r)],
+		Prefab::JumpPad => &[(0.0, -r), (r, r), (-r, r)],
+		Prefab::Stairs => &[
+			(-r, r),
+			(-r, r / 3.0),
+			(-r / 3.0, r / 3.0),
+			(-r / 3.0, -r / 3.0),
+			(r / 3.0, -r / 3.0),
+			(r / 3.0, -r),
+			(r, -r),
+			(r, r),
+		],
+		Prefab::Hideous => &[
+			(0.0, -1.2 * r),
+			(1.2 * r, 0.0),
+			(0.0, 1.2 * r),
+			(-1.2 * r, 0.0),
+		],
+	};
+	let points: Vec<String> = points
+		.iter()
+		.map(|(dx, dy)| format!("{},{}", num(x + dx), num(y + dy)))
+		.collect();
+	writeln!(out, r#"<polygon points="{}" {}/>"#, points.join(" "), style)
+		.unwrap();
+}
+
+/// Draws `map` as an SVG document.
+pub fn render_svg(map: &Map, options: &SvgOptions) -> String {
+	let (low, high) = ground_range(map);
+	let width = LEFT + 16.0 + 0.5;
+	let height = TOP + 16.0 + if options.legend { 2.0 } else { 0.5 };
+
+	let mut out = String::new();
+	writeln!(
+		out,
+		r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}" font-family="sans-serif" text-anchor="middle">"#,
+		-LEFT,
+		-TOP,
+		width,
+		height,
+		width * options.tile_size,
+		height * options.tile_size
+	)
+	.unwrap();
+
+	for x in 0..16 {
+		writeln!(
+			out,
+			r#"<text x="{}" y="-0.3" font-size="0.4">{}</text>"#,
+			x as f32 + 0.5,
+			x
+		)
+		.unwrap();
+	}
+	for y in 0..16 {
+		writeln!(
+			out,
+			r#"<text x="-0.4" y="{}" font-size="0.4">{}</text>"#,
+			y as f32 + 0.65,
+			y
+		)
+		.unwrap();
+	}
+
+	for ((x, y), h) in map.heights.iter() {
+		let color = height_color(*h, low, high);
+		writeln!(
+			out,
+			r##"<rect x="{}" y="{}" width="1" height="1" fill="{}" stroke="#333" stroke-width="0.03"/>"##,
+			x,
+			y,
+			hex(color)
+		)
+		.unwrap();
+		let prefab = map.prefabs.0[y][x];
+		let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+		if options.numbers {
+			let (sy, ty) = if prefab == Prefab::None {
+				(cy, cy + 0.13)
+			} else {
+				(cy - 0.12, cy + 0.4)
+			};
+			writeln!(
+				out,
+				r#"<text x="{}" y="{}" font-size="{}" fill="{}">{}</text>"#,
+				cx,
+				ty,
+				if prefab == Prefab::None { 0.38 } else { 0.26 },
+				ink(color),
+				h.0
+			)
+			.unwrap();
+			symbol(&mut out, prefab, cx, sy);
+		} else {
+			symbol(&mut out, prefab, cx, cy);
+		}
+	}
+
+	// A 2.5 tile arrow along +x, from 4.25 to 1.75 tiles left of the grid,
+	// between rows 7 and 8. The editor's arrow is in the same spot: it's
+	// drawn in world space from x = -12.25 at z = 0, and the pillars there
+	// are moved by -7.5 so that the grid's edges are at -8 and 8.
+	writeln!(
+		out,
+		r#"<path d="M -4.25 8 H -1.75 M -2.75 7.25 L -1.75 8 L -2.75 8.75" fill="none" stroke="red" stroke-width="0.12"/>"#
+	)
+	.unwrap();
+
+	if options.legend {
+		for (i, prefab) in Prefab::ALL[1..].iter().enumerate() {
+			let x = i as f32 * 3.4;
+			symbol(&mut out, *prefab, x + 0.3, 17.0);
+			writeln!(
+				out,
+				r#"<text x="{}" y="17.15" font-size="0.45" text-anchor="start">{:?}</text>"#,
+				x + 0.7,
+				prefab
+			)
+			.unwrap();
+		}
+	}
+
+	out.push_str("</svg>\n");
+	out
+}
+
+impl Map {
+	pub fn to_svg(&self, options: &SvgOptions) -> String {
+		render_svg(self, options)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Height, Parsable};
+
+	#[test]
+	fn document() {
+		let mut map = Map::default();
+		map.heights.0[0][1] = Height(-15);
+		map.prefabs.0[2][3] = Prefab::JumpPad;
+		let svg = map.to_svg(&SvgOptions::default());
+
+		assert!(svg.starts_with(
+			r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-5 -1 21.5 19" width="688" height="608""#
+		));
+		assert!(svg.ends_with("</svg>\n"));
+		assert_eq!(svg.matches("<rect x=").count(), 256);
+		assert_eq!(svg.matches("<polygon").count(), 5);
+		assert!(svg
+			.contains(r##"<rect x="1" y="0" width="1" height="1" fill="#19191e""##));
+		assert!(svg.contains(r##"fill="#fff">-15</text>"##));
+		assert!(svg.contains(r#"stroke="red""#));
+		assert!(svg.contains(">JumpPad</text>"));
+	}
+
+	#[test]
+	fn without_extras() {
+		let svg = Map::default().to_svg(&SvgOptions {
+			tile_size: 10.0,
+			numbers: false,
+			legend: false,
+		});
+		assert!(
+			svg.contains(r#"viewBox="-5 -1 21.5 17.5" width="215" height="175""#)
+		);
+		assert!(!svg.contains("font-size=\"0.38\""));
+		assert!(!svg.contains("<circle"));
+	}
+}
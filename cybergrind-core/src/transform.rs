@@ -0,0 +1,267 @@
+use crate::{Grid, Map, Parsable};
+
+/// The eight ways of turning and mirroring a square.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+	Identity,
+	/// A quarter turn clockwise.
+	Rotate90,
+	Rotate180,
+	Rotate270,
+	FlipHorizontal,
+	FlipVertical,
+	/// Mirrors along the diagonal from the top left to the bottom right.
+	Transpose,
+	/// Mirrors along the diagonal from the top right to the bottom left.
+	AntiTranspose,
+}
+
+impl Symmetry {
+	pub const ALL: [Self; 8] = [
+		Symmetry::Identity,
+		Symmetry::Rotate90,
+		Symmetry::Rotate180,
+		Symmetry::Rotate270,
+		Symmetry::FlipHorizontal,
+		Symmetry::FlipVertical,
+		Symmetry::Transpose,
+		Symmetry::AntiTranspose,
+	];
+
+	/// The symmetry that undoes this one.
+	pub fn inverse(self) -> Self {
+		match self {
+			Symmetry::Rotate90 => Symmetry::Rotate270,
+			Symmetry::Rotate270 => Symmetry::Rotate90,
+			other => other,
+		}
+	}
+
+	/// Where the tile at `(x, y)` is taken from.
+	pub(crate) fn source(self, x: usize, y: usize) -> (usize, usize) {
+		match self {
+			Symmetry::Identity => (x, y),
+			Symmetry::Rotate90 => (y, 15 - x),
+			Symmetry::Rotate180 => (15 - x, 15 - y),
+			Symmetry::Rotate270 => (15 - y, x),
+			Symmetry::FlipHorizontal => (15 - x, y),
+			Symmetry::FlipVertical => (x, 15 - y),
+			Symmetry::Transpose => (y, x),
+			Symmetry::AntiTranspose => (15 - y, 15 - x),
+		}
+	}
+}
+
+impl<T: Parsable + Copy> Grid<T> {
+	/// Builds a new grid where the tile at `(x, y)` is taken from `self` at
+	/// `source(x, y)`, or is the default if that's `None`.
+	fn remap(
+		&self,
+		source: impl Fn(usize, usize) -> Option<(usize, usize)>,
+	) -> Self {
+		let mut grid = Self::default();
+		for (y, row) in grid.0.iter_mut().enumerate() {
+			for (x, item) in row.iter_mut().enumerate() {
+				if let Some((sx, sy)) = source(x, y) {
+					*item = self.0[sy][sx];
+				}
+			}
+		}
+		grid
+	}
+
+	pub fn apply(&self, symmetry: Symmetry) -> Self {
+		self.remap(|x, y| Some(symmetry.source(x, y)))
+	}
+
+	/// Rotates a quarter turn clockwise.
+	pub fn rotate90(&self) -> Self {
+		self.apply(Symmetry::Rotate90)
+	}
+
+	pub fn rotate180(&self) -> Self {
+		self.apply(Symmetry::Rotate180)
+	}
+
+	/// Mirrors left to right.
+	pub fn flip_horizontal(&self) -> Self {
+		self.apply(Symmetry::FlipHorizontal)
+	}
+
+	/// Mirrors top to bottom.
+	pub fn flip_vertical(&self) -> Self {
+		self.apply(Symmetry::FlipVertical)
+	}
+
+	/// Mirrors along the diagonal from the top left to the bottom right.
+	pub fn transpose(&self) -> Self {
+		self.apply(Symmetry::Transpose)
+	}
+
+	/// Moves every tile `dx` to the right and `dy` down. Tiles pushed off the
+	/// edge are dropped and the ones left uncovered are set to the default.
+	pub fn shift(&self, dx: isize, dy: isize) -> Self {
+		self.remap(|x, y| {
+			let (sx, sy) = (x as isize - dx, y as isize - dy);
+			if (0..16).contains(&sx) && (0..16).contains(&sy) {
+				Some((sx as usize, sy as usize))
+			} else {
+				None
+			}
+		})
+	}
+
+	/// Like [`Grid::shift`], but tiles pushed off one edge come back in on
+	/// the opposite one.
+	pub fn wrapping_shift(&self, dx: isize, dy: isize) -> Self {
+		self.remap(|x, y| {
+			Some((
+				(x as isize - dx).rem_euclid(16) as usize,
+				(y as isize - dy).rem_euclid(16) as usize,
+			))
+		})
+	}
+}
+
+/// The same transforms as on [`Grid`], moving heights and prefabs together.
+impl Map {
+	pub fn apply(&self, symmetry: Symmetry) -> Self {
+		Self {
+			heights: self.heights.apply(symmetry),
+			prefabs: self.prefabs.apply(symmetry),
+		}
+	}
+
+	pub fn rotate90(&self) -> Self {
+		Self {
+			heights: self.heights.rotate90(),
+			prefabs: self.prefabs.rotate90(),
+		}
+	}
+
+	pub fn rotate180(&self) -> Self {
+		Self {
+			heights: self.heights.rotate180(),
+			prefabs: self.prefabs.rotate180(),
+		}
+	}
+
+	pub fn flip_horizontal(&self) -> Self {
+		Self {
+			heights: self.heights.flip_horizontal(),
+			prefabs: self.prefabs.flip_horizontal(),
+		}
+	}
+
+	pub fn flip_vertical(&self) -> Self {
+		Self {
+			heights: self.heights.flip_vertical(),
+			prefabs: self.prefabs.flip_vertical(),
+		}
+	}
+
+	pub fn transpose(&self) -> Self {
+		Self {
+			heights: self.heights.transpose(),
+			prefabs: self.prefabs.transpose(),
+		}
+	}
+
+	pub fn shift(&self, dx: isize, dy: isize) -> Self {
+		Self {
+			heights: self.heights.shift(dx, dy),
+			prefabs: self.prefabs.shift(dx, dy),
+		}
+	}
+
+	pub fn wrapping_shift(&self, dx: isize, dy: isize) -> Self {
+		Self {
+			heights: self.heights.wrapping_shift(dx, dy),
+			prefabs: self.prefabs.wrapping_shift(dx, dy),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Height, Prefab};
+
+	fn numbered() -> Grid<Height> {
+		let mut grid = Grid::<Height>::default();
+		for (y, row) in grid.0.iter_mut().enumerate() {
+			for (x, item) in row.iter_mut().enumerate() {
+				*item = Height((y * 16 + x) as i8);
+			}
+		}
+		grid
+	}
+
+	#[test]
+	fn rotations() {
+		let grid = numbered();
+		let r90 = grid.rotate90();
+		assert_eq!(r90.get(15, 0), grid.get(0, 0));
+		assert_eq!(r90.get(0, 0), grid.get(0, 15));
+		assert_eq!(r90.rotate90(), grid.rotate180());
+		assert_eq!(r90.rotate90().rotate90().rotate90(), grid);
+	}
+
+	#[test]
+	fn flips() {
+		let grid = numbered();
+		assert_eq!(grid.flip_horizontal().get(0, 3), grid.get(15, 3));
+		assert_eq!(grid.flip_vertical().get(2, 0), grid.get(2, 15));
+		assert_eq!(grid.flip_horizontal().flip_horizontal(), grid);
+		assert_eq!(grid.flip_horizontal().flip_vertical(), grid.rotate180());
+		assert_eq!(grid.rotate90().flip_horizontal(), grid.transpose());
+		assert_eq!(grid.transpose().get(3, 7), grid.get(7, 3));
+	}
+
+	#[test]
+	fn symmetries() {
+		let grid = numbered();
+		assert_eq!(grid.apply(Symmetry::Identity), grid);
+		assert_eq!(grid.apply(Symmetry::Rotate270), grid.rotate180().rotate90());
+		assert_eq!(
+			grid.apply(Symmetry::AntiTranspose),
+			grid.rotate180().transpose()
+		);
+		for symmetry in Symmetry::ALL.iter() {
+			assert_eq!(grid.apply(*symmetry).apply(symmetry.inverse()), grid);
+		}
+		for (i, a) in Symmetry::ALL.iter().enumerate() {
+			for b in &Symmetry::ALL[i + 1..] {
+				assert_ne!(grid.apply(*a), grid.apply(*b), "{:?} {:?}", a, b);
+			}
+		}
+	}
+
+	#[test]
+	fn shifts() {
+		let grid = numbered();
+		let shifted = grid.shift(2, -1);
+		assert_eq!(shifted.get(2, 0), grid.get(0, 1));
+		assert_eq!(shifted.get(1, 4), Some(&Height(0)));
+		assert_eq!(shifted.get(5, 15), Some(&Height(0)));
+
+		let wrapped = grid.wrapping_shift(2, -1);
+		assert_eq!(wrapped.get(2, 0), grid.get(0, 1));
+		assert_eq!(wrapped.get(1, 4), grid.get(15, 5));
+		assert_eq!(wrapped.get(5, 15), grid.get(3, 0));
+		assert_eq!(wrapped.wrapping_shift(-2, 1), grid);
+	}
+
+	#[test]
+	fn map_moves_both_layers() {
+		let mut map = Map {
+			heights: numbered(),
+			prefabs: Grid::default(),
+		};
+		map.prefabs.0[0][0] = Prefab::Hideous;
+		let rotated = map.rotate90();
+		assert_eq!(rotated.heights.get(15, 0), Some(&Height(0)));
+		assert_eq!(rotated.prefabs.get(15, 0), Some(&Prefab::Hideous));
+		assert_eq!(map.shift(1, 1).prefabs.get(1, 1), Some(&Prefab::Hideous));
+	}
+}
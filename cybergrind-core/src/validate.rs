@@ -0,0 +1,335 @@
+use std::{cmp::Reverse, fmt};
+
+use crate::{neighbours, Map, Prefab};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+	Info,
+	Warning,
+	Error,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Severity::Info => write!(f, "info"),
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error => write!(f, "error"),
+		}
+	}
+}
+
+/// A problem found by a [`Rule`], pointing at the tiles involved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub rule: &'static str,
+	pub message: String,
+	/// `(x, y)` positions of the offending tiles.
+	pub tiles: Vec<(usize, usize)>,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}[{}]", self.severity, self.rule)?;
+		for (i, (x, y)) in self.tiles.iter().enumerate() {
+			write!(f, "{}({}, {})", if i == 0 { " " } else { ", " }, x, y)?;
+		}
+		write!(f, ": {}", self.message)
+	}
+}
+
+pub trait Rule {
+	/// Short kebab-case name, used to tell diagnostics apart.
+	fn id(&self) -> &'static str;
+
+	fn check(&self, map: &Map, diagnostics: &mut Vec<Diagnostic>);
+}
+
+/// A set of [`Rule`]s to run against a map. The default set has every rule
+/// in this module.
+pub struct Validator {
+	rules: Vec<Box<dyn Rule>>,
+}
+
+impl Validator {
+	pub fn empty() -> Self {
+		Self { rules: Vec::new() }
+	}
+
+	pub fn with_rule(mut self, rule: impl Rule + 'static) -> Self {
+		self.rules.push(Box::new(rule));
+		self
+	}
+
+	/// Runs every rule, returning the diagnostics most severe first.
+	pub fn validate(&self, map: &Map) -> Vec<Diagnostic> {
+		let mut diagnostics = Vec::new();
+		for rule in self.rules.iter() {
+			rule.check(map, &mut diagnostics);
+		}
+		diagnostics.sort_by_key(|d| Reverse(d.severity));
+		diagnostics
+	}
+}
+
+impl Default for Validator {
+	fn default() -> Self {
+		Self::empty()
+			.with_rule(UnknownPrefab)
+			.with_rule(PrefabOnPit)
+			.with_rule(JumpPadLanding { reach: 2 })
+			.with_rule(FlatStairs)
+			.with_rule(HideousCount { max: 1 })
+	}
+}
+
+/// Runs the default [`Validator`] on `map`.
+pub fn validate(map: &Map) -> Vec<Diagnostic> {
+	Validator::default().validate(map)
+}
+
+/// Prefabs this library doesn't know, which the game may not either. One
+/// error for each unknown character.
+pub struct UnknownPrefab;
+
+impl Rule for UnknownPrefab {
+	fn id(&self) -> &'static str {
+		"unknown-prefab"
+	}
+
+	fn check(&self, map: &Map, diagnostics: &mut Vec<Diagnostic>) {
+		let mut unknown: Vec<(char, Vec<(usize, usize)>)> = Vec::new();
+		for (pos, prefab) in map.prefabs.iter() {
+			if let Prefab::Unknown(c) = *prefab {
+				let c = c.get();
+				match unknown.iter_mut().find(|(u, _)| *u == c) {
+					Some((_, tiles)) => tiles.push(pos),
+					None => unknown.push((c, vec![pos])),
+				}
+			}
+		}
+		for (c, tiles) in unknown {
+			diagnostics.push(Diagnostic {
+				severity: Severity::Error,
+				rule: self.id(),
+				message: format!("unknown prefab `{}`", c),
+				tiles,
+			});
+		}
+	}
+}
+
+/// Prefabs placed on pit tiles, which will never be reachable. An error, as
+/// the pattern can't be played as it was meant to be.
+pub struct PrefabOnPit;
+
+impl Rule for PrefabOnPit {
+	fn id(&self) -> &'static str {
+		"prefab-on-pit"
+	}
+
+	fn check(&self, map: &Map, diagnostics: &mut Vec<Diagnostic>) {
+		for (pos, prefab) in map.prefabs.iter() {
+			let height = map.heights.0[pos.1][pos.0];
+			if *prefab != Prefab::None && height.is_pit() {
+				diagnostics.push(Diagnostic {
+					severity: Severity::Error,
+					rule: self.id(),
+					message: format!("prefab `{}` is placed in a pit", prefab.to_char()),
+					tiles: vec![pos],
+				});
+			}
+		}
+	}
+}
+
+/// Jump pads with no solid ground within `reach` tiles of them.
+pub struct JumpPadLanding {
+	pub reach: usize,
+}
+
+impl Rule for JumpPadLanding {
+	fn id(&self) -> &'static str {
+		"jump-pad-landing"
+	}
+
+	fn check(&self, map: &Map, diagnostics: &mut Vec<Diagnostic>) {
+		for ((x, y), prefab) in map.prefabs.iter() {
+			if *prefab != Prefab::JumpPad || map.heights.0[y][x].is_pit() {
+				continue;
+			}
+			let landing = map.heights.iter().any(|((lx, ly), height)| {
+				(lx, ly) != (x, y)
+					&& lx.max(x) - lx.min(x) <= self.reach
+					&& ly.max(y) - ly.min(y) <= self.reach
+					&& !height.is_pit()
+					&& map.prefabs.0[ly][lx] != Prefab::JumpPad
+			});
+			if !landing {
+				diagnostics.push(Diagnostic {
+					severity: Severity::Warning,
+					rule: self.id(),
+					message: format!(
+						"jump pad has nothing to land on within {} tiles",
+						self.reach
+					),
+					tiles: vec![(x, y)],
+				});
+			}
+		}
+	}
+}
+
+/// Stairs whose neighbours are all at the same height as them.
+pub struct FlatStairs;
+
+impl Rule for FlatStairs {
+	fn id(&self) -> &'static str {
+		"flat-stairs"
+	}
+
+	fn check(&self, map: &Map, diagnostics: &mut Vec<Diagnostic>) {
+		for ((x, y), prefab) in map.prefabs.iter() {
+			let height = map.heights.0[y][x];
+			if *prefab != Prefab::Stairs || height.is_pit() {
+				continue;
+			}
+			let bridges = neighbours((x, y)).any(|(nx, ny)| {
+				let other = map.heights.0[ny][nx];
+				!other.is_pit() && other != height
+			});
+			if !bridges {
+				diagnostics.push(Diagnostic {
+					severity: Severity::Warning,
+					rule: self.id(),
+					message: "stairs have no height difference to bridge".to_string(),
+					tiles: vec![(x, y)],
+				});
+			}
+		}
+	}
+}
+
+/// More Hideous Mass spawns than `max`.
+pub struct HideousCount {
+	pub max: usize,
+}
+
+impl Rule for HideousCount {
+	fn id(&self) -> &'static str {
+		"hideous-count"
+	}
+
+	fn check(&self, map: &Map, diagnostics: &mut Vec<Diagnostic>) {
+		let tiles: Vec<_> = map
+			.prefabs
+			.iter()
+			.filter(|(_, prefab)| **prefab == Prefab::Hideous)
+			.map(|(pos, _)| pos)
+			.collect();
+		if tiles.len() > self.max {
+			diagnostics.push(Diagnostic {
+				severity: Severity::Warning,
+				rule: self.id(),
+				message: format!(
+					"{} hideous prefabs placed, at most {} are allowed",
+					tiles.len(),
+					self.max
+				),
+				tiles,
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Height, Parsable, UnknownChar};
+
+	fn rules(map: &Map) -> Vec<(&'static str, Vec<(usize, usize)>)> {
+		validate(map)
+			.into_iter()
+			.map(|d| (d.rule, d.tiles))
+			.collect()
+	}
+
+	#[test]
+	fn default_map_is_clean() {
+		assert!(validate(&Map::default()).is_empty());
+	}
+
+	#[test]
+	fn unknown_prefab() {
+		let mut map = Map::default();
+		map.prefabs.0[0][3] = Prefab::Unknown(UnknownChar('x'));
+		map.prefabs.0[2][1] = Prefab::Unknown(UnknownChar('Q'));
+		map.prefabs.0[5][0] = Prefab::Unknown(UnknownChar('x'));
+		assert_eq!(
+			rules(&map),
+			vec![
+				("unknown-prefab", vec![(3, 0), (0, 5)]),
+				("unknown-prefab", vec![(1, 2)]),
+			]
+		);
+		assert_eq!(validate(&map)[1].message, "unknown prefab `Q`");
+		assert_eq!(validate(&map)[1].severity, Severity::Error);
+	}
+
+	#[test]
+	fn prefab_on_pit() {
+		let mut map = Map::default();
+		map.heights.0[0][0] = Height(-15);
+		map.prefabs.0[0][0] = Prefab::Melee;
+		map.heights.0[0][1] = Height(-14);
+		map.prefabs.0[0][1] = Prefab::Melee;
+		assert_eq!(rules(&map), vec![("prefab-on-pit", vec![(0, 0)])]);
+		assert_eq!(validate(&map)[0].message, "prefab `n` is placed in a pit");
+	}
+
+	#[test]
+	fn jump_pad_landing() {
+		let mut map = Map::default();
+		for row in map.heights.0.iter_mut() {
+			for height in row.iter_mut() {
+				*height = Height(-15);
+			}
+		}
+		map.heights.0[8][8] = Height(0);
+		map.prefabs.0[8][8] = Prefab::JumpPad;
+		assert_eq!(rules(&map), vec![("jump-pad-landing", vec![(8, 8)])]);
+
+		map.heights.0[10][10] = Height(3);
+		assert!(validate(&map).is_empty());
+	}
+
+	#[test]
+	fn flat_stairs() {
+		let mut map = Map::default();
+		map.prefabs.0[4][4] = Prefab::Stairs;
+		assert_eq!(rules(&map), vec![("flat-stairs", vec![(4, 4)])]);
+
+		map.heights.0[4][5] = Height(1);
+		assert!(validate(&map).is_empty());
+	}
+
+	#[test]
+	fn hideous_count() {
+		let mut map = Map::default();
+		map.prefabs.0[0][0] = Prefab::Hideous;
+		assert!(validate(&map).is_empty());
+		map.prefabs.0[3][5] = Prefab::Hideous;
+		assert_eq!(rules(&map), vec![("hideous-count", vec![(0, 0), (5, 3)])]);
+	}
+
+	#[test]
+	fn display() {
+		let mut map = Map::default();
+		map.prefabs.0[4][4] = Prefab::Stairs;
+		assert_eq!(
+			validate(&map)[0].to_string(),
+			"warning[flat-stairs] (4, 4): stairs have no height difference to bridge"
+		);
+	}
+}
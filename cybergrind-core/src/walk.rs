@@ -0,0 +1,305 @@
+use std::collections::VecDeque;
+
+use crate::{neighbours, Grid, Height, Map, Prefab};
+
+/// How well the player gets around. Heights are in the same units as
+/// [`Height`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Movement {
+	/// Tallest ledge that can be walked up without jumping.
+	pub step_height: i8,
+	/// Tallest ledge that can be jumped up onto.
+	pub jump_height: i8,
+	/// Tallest ledge a stairs prefab on either side lets the player climb.
+	pub stairs_height: i8,
+	/// How many tiles away a jump pad can throw the player.
+	pub jump_pad_reach: usize,
+	/// How far above itself a jump pad can throw the player.
+	pub jump_pad_height: i8,
+}
+
+impl Default for Movement {
+	fn default() -> Self {
+		Self {
+			step_height: 0,
+			jump_height: 2,
+			stairs_height: 4,
+			jump_pad_reach: 4,
+			jump_pad_height: 10,
+		}
+	}
+}
+
+/// The way the player gets from one tile to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+	Walk,
+	Jump,
+	Stairs,
+	JumpPad,
+	/// Dropping down a ledge too tall to walk back up.
+	Fall,
+}
+
+/// The tiles of a map as a directed graph of the moves between them. Pits
+/// aren't part of the graph, falling into one isn't getting anywhere.
+#[derive(Debug, Clone)]
+pub struct Walkability {
+	moves: Vec<Vec<((usize, usize), Move)>>,
+	standable: [[bool; 16]; 16],
+}
+
+fn index((x, y): (usize, usize)) -> usize {
+	y * 16 + x
+}
+
+fn tile(i: usize) -> (usize, usize) {
+	(i % 16, i / 16)
+}
+
+impl Walkability {
+	pub fn new(
+		heights: &Grid<Height>,
+		prefabs: &Grid<Prefab>,
+		movement: &Movement,
+	) -> Self {
+		let mut standable = [[false; 16]; 16];
+		for ((x, y), height) in heights.iter() {
+			standable[y][x] = !height.is_pit();
+		}
+
+		let mut moves = vec![Vec::new(); 256];
+		for ((x, y), height) in heights.iter() {
+			if !standable[y][x] {
+				continue;
+			}
+			let from = &mut moves[index((x, y))];
+			let stairs = prefabs.0[y][x] == Prefab::Stairs;
+			for (nx, ny) in neighbours((x, y)) {
+				if !standable[ny][nx] {
+					continue;
+				}
+				let rise = heights.0[ny][nx].0 as i16 - height.0 as i16;
+				let stairs = stairs || prefabs.0[ny][nx] == Prefab::Stairs;
+				let m = if rise <= movement.step_height as i16 {
+					if -rise > movement.step_height as i16 {
+						Move::Fall
+					} else {
+						Move::Walk
+					}
+				} else if stairs && rise <= movement.stairs_height as i16 {
+					Move::Stairs
+				} else if rise <= movement.jump_height as i16 {
+					Move::Jump
+				} else {
+					continue;
+				};
+				from.push(((nx, ny), m));
+			}
+
+			if prefabs.0[y][x] == Prefab::JumpPad {
+				let reach = movement.jump_pad_reach;
+				let top = height.0 as i16 + movement.jump_pad_height as i16;
+				for ((tx, ty), target) in heights.iter() {
+					let close =
+						tx.max(x) - tx.min(x) <= reach && ty.max(y) - ty.min(y) <= reach;
+					if close
+						&& (tx, ty) != (x, y)
+						&& standable[ty][tx]
+						&& target.0 as i16 <= top
+						&& !from.iter().any(|(to, _)| *to == (tx, ty))
+					{
+						from.push(((tx, ty), Move::JumpPad));
+					}
+				}
+			}
+		}
+
+		Self { moves, standable }
+	}
+
+	/// Whether the player can stand on `tile` at all.
+	pub fn is_standable(&self, (x, y): (usize, usize)) -> bool {
+		x < 16 && y < 16 && self.standable[y][x]
+	}
+
+	/// The tiles the player can get to in a single move from `tile`.
+	pub fn moves_from(&self, tile: (usize, usize)) -> &[((usize, usize), Move)] {
+		if self.is_standable(tile) {
+			&self.moves[index(tile)]
+		} else {
+			&[]
+		}
+	}
+
+	/// Breadth-first search from `from`, returning the tile each reached tile
+	/// was first reached from.
+	fn search(&self, from: (usize, usize)) -> Vec<Option<usize>> {
+		let mut came_from = vec![None; 256];
+		if !self.is_standable(from) {
+			return came_from;
+		}
+		let mut queue = VecDeque::new();
+		came_from[index(from)] = Some(index(from));
+		queue.push_back(from);
+		while let Some(at) = queue.pop_front() {
+			for (to, _) in self.moves_from(at) {
+				if came_from[index(*to)].is_none() {
+					came_from[index(*to)] = Some(index(at));
+					queue.push_back(*to);
+				}
+			}
+		}
+		came_from
+	}
+
+	/// Every tile reachable from `from`, including itself.
+	pub fn reachable_from(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
+		self
+			.search(from)
+			.iter()
+			.enumerate()
+			.filter(|(_, came_from)| came_from.is_some())
+			.map(|(i, _)| tile(i))
+			.collect()
+	}
+
+	/// The shortest sequence of tiles from `from` to `to`, both included.
+	pub fn route(
+		&self,
+		from: (usize, usize),
+		to: (usize, usize),
+	) -> Option<Vec<(usize, usize)>> {
+		if !self.is_standable(to) {
+			return None;
+		}
+		let came_from = self.search(from);
+		let mut route = vec![to];
+		let mut at = index(to);
+		while at != index(from) {
+			at = came_from[at]?;
+			route.push(tile(at));
+		}
+		route.reverse();
+		Some(route)
+	}
+
+	/// Groups of tiles where every tile can be reached from every other tile
+	/// in the group. A well connected arena has exactly one.
+	pub fn components(&self) -> Vec<Vec<(usize, usize)>> {
+		let reach: Vec<Vec<bool>> = (0..256)
+			.map(|i| self.search(tile(i)).iter().map(Option::is_some).collect())
+			.collect();
+		let mut component = vec![None; 256];
+		let mut components: Vec<Vec<(usize, usize)>> = Vec::new();
+		for i in 0..256 {
+			if component[i].is_some() || !self.is_standable(tile(i)) {
+				continue;
+			}
+			let id = components.len();
+			let mut tiles = Vec::new();
+			for j in i..256 {
+				if reach[i][j] && reach[j][i] {
+					component[j] = Some(id);
+					tiles.push(tile(j));
+				}
+			}
+			components.push(tiles);
+		}
+		components
+	}
+}
+
+impl Map {
+	pub fn walkability(&self, movement: &Movement) -> Walkability {
+		Walkability::new(&self.heights, &self.prefabs, movement)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Parsable;
+
+	fn map_with(f: impl Fn(&mut Map)) -> Map {
+		let mut map = Map::default();
+		f(&mut map);
+		map
+	}
+
+	#[test]
+	fn flat_map_is_connected() {
+		let walk = Map::default().walkability(&Movement::default());
+		let components = walk.components();
+		assert_eq!(components.len(), 1);
+		assert_eq!(components[0].len(), 256);
+		assert_eq!(walk.moves_from((0, 0)).len(), 2);
+	}
+
+	#[test]
+	fn pits_split_islands() {
+		let map = map_with(|map| {
+			for row in map.heights.0.iter_mut() {
+				row[8] = Height(-15);
+			}
+		});
+		let walk = map.walkability(&Movement::default());
+		let components = walk.components();
+		assert_eq!(components.len(), 2);
+		assert_eq!(components[0].len(), 8 * 16);
+		assert!(walk.route((0, 0), (15, 15)).is_none());
+		assert!(!walk.is_standable((8, 3)));
+	}
+
+	#[test]
+	fn ledges() {
+		let map = map_with(|map| {
+			for row in map.heights.0.iter_mut() {
+				for height in row[8..].iter_mut() {
+					*height = Height(3);
+				}
+			}
+		});
+		let walk = map.walkability(&Movement::default());
+		assert!(walk.moves_from((8, 0)).contains(&((7, 0), Move::Fall)));
+		assert_eq!(walk.components().len(), 2);
+		assert!(walk.route((0, 0), (15, 0)).is_none());
+		assert!(walk.route((15, 0), (0, 0)).is_some());
+
+		let mut with_stairs = map;
+		with_stairs.prefabs.0[5][8] = Prefab::Stairs;
+		let walk = with_stairs.walkability(&Movement::default());
+		assert_eq!(walk.components().len(), 1);
+		let route = walk.route((0, 5), (15, 5)).unwrap();
+		assert_eq!(route.len(), 16);
+		assert_eq!(route[7..9], [(7, 5), (8, 5)]);
+	}
+
+	#[test]
+	fn jump_pads() {
+		let map = map_with(|map| {
+			for row in map.heights.0.iter_mut() {
+				for height in row[8..].iter_mut() {
+					*height = Height(8);
+				}
+			}
+			map.prefabs.0[0][6] = Prefab::JumpPad;
+		});
+		let walk = map.walkability(&Movement::default());
+		assert_eq!(
+			walk.route((0, 0), (9, 0)),
+			Some(vec![
+				(0, 0),
+				(1, 0),
+				(2, 0),
+				(3, 0),
+				(4, 0),
+				(5, 0),
+				(6, 0),
+				(9, 0)
+			])
+		);
+		assert!(walk.moves_from((6, 0)).contains(&((10, 4), Move::JumpPad)));
+		assert!(!walk.moves_from((6, 0)).iter().any(|(to, _)| *to == (11, 0)));
+	}
+}
@@ -0,0 +1,610 @@
+//! Arenas in the style of example patterns, by wave function collapse.
+//!
+//! Every `size` by `size` square of the examples is learned as a pattern of
+//! prefabs, pits and heights relative to each other, so a ramp is the same
+//! pattern wherever it starts. New maps are made of those patterns
+//! overlapping in ways the examples allow, then lifted to the examples'
+//! lowest height.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{rng::Rng, Grid, Height, Map, Prefab, Symmetry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WfcOptions {
+	/// Width of the learned patterns, 2 or 3. Bigger copies more of the
+	/// examples as they are.
+	pub size: usize,
+	/// Also learn every turned and mirrored copy of the examples.
+	pub symmetries: bool,
+	/// How many times to start over when the patterns paint themselves into
+	/// a corner.
+	pub attempts: u32,
+}
+
+impl Default for WfcOptions {
+	fn default() -> Self {
+		Self {
+			size: 3,
+			symmetries: true,
+			attempts: 10,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WfcError {
+	NoExamples,
+	/// Patterns can only be 2 or 3 tiles wide.
+	InvalidSize(usize),
+	/// Every attempt ran into a spot no pattern fits.
+	Contradiction,
+}
+
+impl fmt::Display for WfcError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			WfcError::NoExamples => write!(f, "no example patterns to learn from"),
+			WfcError::InvalidSize(size) => {
+				write!(f, "patterns can be 2 or 3 tiles wide, not {}", size)
+			}
+			WfcError::Contradiction => write!(
+				f,
+				"couldn't fit the learned patterns together, try another seed"
+			),
+		}
+	}
+}
+
+impl std::error::Error for WfcError {}
+
+/// A tile of a pattern. Heights are relative to the lowest one in the
+/// pattern, pits have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Cell {
+	height: Option<i8>,
+	prefab: Prefab,
+}
+
+/// What was learned from the examples.
+#[derive(Debug, Clone)]
+pub struct Wfc {
+	size: usize,
+	attempts: u32,
+	patterns: Vec<Vec<Cell>>,
+	weights: Vec<f32>,
+	/// Sets of patterns that can overlap others, many patterns sharing each.
+	/// Every pattern is in one bucket for each offset at most.
+	buckets: Vec<Vec<usize>>,
+	/// For each pattern and offset, the bucket of patterns that can be
+	/// placed that far from it.
+	bucket_of: Vec<usize>,
+	/// For each bucket, how many patterns lead to it.
+	supporters: Vec<u32>,
+	/// Lowest height of the examples, where generated maps are lifted to.
+	base: i8,
+}
+
+/// The patterns still possible at each spot a pattern can be placed, and
+/// the sums needed for their entropy.
+struct Wave {
+	possible: Vec<bool>,
+	counts: Vec<usize>,
+	weight_sums: Vec<f32>,
+	log_sums: Vec<f32>,
+	/// For each spot and bucket, how many of the spot's patterns lead to
+	/// that bucket. The bucket's patterns can't be placed next to the spot
+	/// once this runs out.
+	support: Vec<u32>,
+}
+
+impl Wfc {
+	pub fn learn(
+		examples: &[Map],
+		options: &WfcOptions,
+	) -> Result<Self, WfcError> {
+		let size = options.size;
+		if !(2..=3).contains(&size) {
+			return Err(WfcError::InvalidSize(size));
+		}
+		if examples.is_empty() {
+			return Err(WfcError::NoExamples);
+		}
+		let symmetries: &[Symmetry] = if options.symmetries {
+			&Symmetry::ALL
+		} else {
+			&[Symmetry::Identity]
+		};
+
+		let mut index = HashMap::new();
+		let mut patterns = Vec::new();
+		let mut weights: Vec<f32> = Vec::new();
+		for example in examples {
+			for symmetry in symmetries {
+				let map = example.apply(*symmetry);
+				for y in 0..=16 - size {
+					for x in 0..=16 - size {
+						let pattern = Self::pattern_at(&map, size, x, y);
+						let i = *index.entry(pattern.clone()).or_insert_with(|| {
+							patterns.push(pattern);
+							weights.push(0.0);
+							patterns.len() - 1
+						});
+						weights[i] += 1.0;
+					}
+				}
+			}
+		}
+		let base = examples
+			.iter()
+			.flat_map(|m| m.heights.iter().map(|(_, h)| *h))
+			.filter(|h| !h.is_pit())
+			.min()
+			.unwrap_or(Height(0))
+			.0;
+
+		let mut wfc = Self {
+			size,
+			attempts: options.attempts.max(1),
+			patterns,
+			weights,
+			buckets: Vec::new(),
+			bucket_of: Vec::new(),
+			supporters: Vec::new(),
+			base,
+		};
+		wfc.fill_buckets();
+		Ok(wfc)
+	}
+
+	/// How many different patterns were learned.
+	pub fn pattern_count(&self) -> usize {
+		self.patterns.len()
+	}
+
+	fn pattern_at(map: &Map, size: usize, x: usize, y: usize) -> Vec<Cell> {
+		let mut cells: Vec<_> = (0..size * size)
+			.map(|i| {
+				let (cx, cy) = (x + i % size, y + i / size);
+				let height = map.heights.0[cy][cx];
+				Cell {
+					height: Some(height.0).filter(|_| !height.is_pit()),
+					prefab: map.prefabs.0[cy][cx],
+				}
+			})
+			.collect();
+		let low = cells.iter().filter_map(|c| c.height).min().unwrap_or(0);
+		for cell in cells.iter_mut() {
+			cell.height = cell.height.map(|h| h - low);
+		}
+		cells
+	}
+
+	/// Offsets go from `-(size - 1)` to `size - 1` both ways.
+	fn span(&self) -> usize {
+		2 * self.size - 1
+	}
+
+	fn offset(&self, o: usize) -> (isize, isize) {
+		let reach = self.size as isize - 1;
+		(
+			(o % self.span()) as isize - reach,
+			(o / self.span()) as isize - reach,
+		)
+	}
+
+	/// The tiles of pattern `p` that another pattern placed `(dx, dy)` from
+	/// it covers, with heights relative to the lowest of them.
+	fn overlap(&self, p: usize, (dx, dy): (isize, isize)) -> Vec<Cell> {
+		let n = self.size as isize;
+		let mut cells = Vec::new();
+		for y in dy.max(0)..n.min(n + dy) {
+			for x in dx.max(0)..n.min(n + dx) {
+				cells.push(self.patterns[p][(y * n + x) as usize]);
+			}
+		}
+		let low = cells.iter().filter_map(|c| c.height).min().unwrap_or(0);
+		for cell in cells.iter_mut() {
+			cell.height = cell.height.map(|h| h - low);
+		}
+		cells
+	}
+
+	/// Works out which patterns can overlap which. Pattern `q` can be placed
+	/// `(dx, dy)` from `p` when the tiles they share match, with the heights
+	/// all apart by the same amount.
+	fn fill_buckets(&mut self) {
+		let span = self.span();
+		let count = self.patterns.len();
+		// the first bucket is for overlaps no pattern matches
+		self.buckets = vec![Vec::new()];
+		self.bucket_of = vec![0; count * span * span];
+		for o in 0..span * span {
+			let (dx, dy) = self.offset(o);
+			let mut by_overlap: HashMap<Vec<Cell>, Vec<usize>> = HashMap::new();
+			for q in 0..count {
+				by_overlap
+					.entry(self.overlap(q, (-dx, -dy)))
+					.or_default()
+					.push(q);
+			}
+			let mut bucket_of_overlap = HashMap::new();
+			for p in 0..count {
+				let overlap = self.overlap(p, (dx, dy));
+				let bucket = match by_overlap.get(&overlap) {
+					None => 0,
+					Some(qs) => *bucket_of_overlap.entry(overlap).or_insert_with(|| {
+						self.buckets.push(qs.clone());
+						self.buckets.len() - 1
+					}),
+				};
+				self.bucket_of[p * span * span + o] = bucket;
+			}
+		}
+		self.supporters = vec![0; self.buckets.len()];
+		for &bucket in &self.bucket_of {
+			self.supporters[bucket] += 1;
+		}
+	}
+
+	/// Makes a map from `seed`. The same seed always makes the same map.
+	pub fn generate(&self, seed: u64) -> Result<Map, WfcError> {
+		let mut rng = Rng::new(seed);
+		for _ in 0..self.attempts {
+			if let Some(chosen) = self.collapse(&mut rng) {
+				return Ok(self.build(&chosen));
+			}
+		}
+		Err(WfcError::Contradiction)
+	}
+
+	/// Spots a pattern can be placed in each direction.
+	fn places(&self) -> usize {
+		17 - self.size
+	}
+
+	/// The spot `(dx, dy)` from `spot`, if it's on the map.
+	fn neighbour(&self, spot: usize, (dx, dy): (isize, isize)) -> Option<usize> {
+		let places = self.places() as isize;
+		let (x, y) = (spot as isize % places + dx, spot as isize / places + dy);
+		if (0..places).contains(&x) && (0..places).contains(&y) {
+			Some((y * places + x) as usize)
+		} else {
+			None
+		}
+	}
+
+	/// Picks a pattern for every spot, or `None` on a contradiction.
+	fn collapse(&self, rng: &mut Rng) -> Option<Vec<usize>> {
+		let count = self.patterns.len();
+		let spots = self.places() * self.places();
+		let span = self.span();
+		let weight_sum: f32 = self.weights.iter().sum();
+		let log_sum: f32 = self.weights.iter().map(|w| w * w.ln()).sum();
+		let mut wave = Wave {
+			possible: vec![true; spots * count],
+			counts: vec![count; spots],
+			weight_sums: vec![weight_sum; spots],
+			log_sums: vec![log_sum; spots],
+			support: self.supporters.repeat(spots),
+		};
+
+		// patterns that nothing can be placed next to only fit at the edges
+		let mut removed = Vec::new();
+		for spot in 0..spots {
+			for o in 0..span * span {
+				if self.offset(o) == (0, 0)
+					|| self.neighbour(spot, self.offset(o)).is_none()
+				{
+					continue;
+				}
+				for p in 0..count {
+					if self.bucket_of[p * span * span + o] == 0 {
+						self.remove(&mut wave, spot, p, &mut removed);
+					}
+				}
+			}
+		}
+		if !self.propagate(&mut wave, &mut removed) {
+			return None;
+		}
+
+		loop {
+			let mut lowest = None;
+			for spot in 0..spots {
+				if wave.counts[spot] > 1 {
+					let (w, l) = (wave.weight_sums[spot], wave.log_sums[spot]);
+					let entropy = w.ln() - l / w + rng.next_f32() * 1e-4;
+					if !matches!(lowest, Some((_, e)) if e <= entropy) {
+						lowest = Some((spot, entropy));
+					}
+				}
+			}
+			let spot = match lowest {
+				Some((spot, _)) => spot,
+				None => break,
+			};
+
+			let possible = &wave.possible[spot * count..(spot + 1) * count];
+			let mut pick = rng.next_f32() * wave.weight_sums[spot];
+			let mut chosen = 0;
+			for p in (0..count).filter(|p| possible[*p]) {
+				chosen = p;
+				pick -= self.weights[p];
+				if pick < 0.0 {
+					break;
+				}
+			}
+			for p in (0..count).filter(|p| *p != chosen) {
+				self.remove(&mut wave, spot, p, &mut removed);
+			}
+			if !self.propagate(&mut wave, &mut removed) {
+				return None;
+			}
+		}
+
+		(0..spots)
+			.map(|spot| (0..count).find(|p| wave.possible[spot * count + p]))
+			.collect()
+	}
+
+	/// Takes pattern `p` out of `spot` if it's still there, noting it in
+	/// `removed` to be propagated.
+	fn remove(
+		&self,
+		wave: &mut Wave,
+		spot: usize,
+		p: usize,
+		removed: &mut Vec<(usize, usize)>,
+	) {
+		let possible = &mut wave.possible[spot * self.patterns.len() + p];
+		if !*possible {
+			return;
+		}
+		*possible = false;
+		let w = self.weights[p];
+		wave.counts[spot] -= 1;
+		wave.weight_sums[spot] -= w;
+		wave.log_sums[spot] -= w * w.ln();
+		removed.push((spot, p));
+	}
+
+	/// Takes patterns that lost their last support out of the spots around
+	/// those in `removed`, and around those, returning `false` if a spot
+	/// runs out.
+	fn propagate(
+		&self,
+		wave: &mut Wave,
+		removed: &mut Vec<(usize, usize)>,
+	) -> bool {
+		let span = self.span();
+		let buckets = self.buckets.len();
+		while let Some((spot, p)) = removed.pop() {
+			for o in 0..span * span {
+				let bucket = self.bucket_of[p * span * span + o];
+				let neighbour = match self.neighbour(spot, self.offset(o)) {
+					Some(neighbour) if bucket != 0 && neighbour != spot => neighbour,
+					_ => continue,
+				};
+				let support = &mut wave.support[spot * buckets + bucket];
+				*support -= 1;
+				if *support > 0 {
+					continue;
+				}
+				for &q in &self.buckets[bucket] {
+					self.remove(wave, neighbour, q, removed);
+				}
+				if wave.counts[neighbour] == 0 {
+					removed.clear();
+					return false;
+				}
+			}
+		}
+		true
+	}
+
+	/// Lays the chosen patterns out into a map, working out each one's
+	/// height from the ones it overlaps.
+	fn build(&self, chosen: &[usize]) -> Map {
+		let places = self.places();
+		let n = self.size;
+		let cell = |spot: usize, x: usize, y: usize| {
+			let (sx, sy) = (spot % places, spot / places);
+			self.patterns[chosen[spot]][(y - sy) * n + x - sx]
+		};
+
+		// how far each placed pattern is lifted, spreading out from the first
+		let mut lift: Vec<Option<i32>> = vec![None; chosen.len()];
+		for first in 0..chosen.len() {
+			if lift[first].is_some() {
+				continue;
+			}
+			lift[first] = Some(0);
+			let mut stack = vec![first];
+			while let Some(spot) = stack.pop() {
+				let (sx, sy) = (spot % places, spot / places);
+				let neighbours = [
+					(sx.wrapping_sub(1), sy),
+					(sx + 1, sy),
+					(sx, sy.wrapping_sub(1)),
+					(sx, sy + 1),
+				];
+				for &(nx, ny) in neighbours.iter() {
+					if nx >= places || ny >= places {
+						continue;
+					}
+					let other = ny * places + nx;
+					if lift[other].is_some() {
+						continue;
+					}
+					// any solid tile both patterns cover ties their heights
+					let shared = (sy.max(ny)..sy.min(ny) + n)
+						.flat_map(|y| (sx.max(nx)..sx.min(nx) + n).map(move |x| (x, y)))
+						.find_map(|(x, y)| {
+							Some((cell(spot, x, y).height?, cell(other, x, y).height?))
+						});
+					if let Some((a, b)) = shared {
+						lift[other] = Some(lift[spot].unwrap() + a as i32 - b as i32);
+						stack.push(other);
+					}
+				}
+			}
+		}
+
+		let mut heights = [[None; 16]; 16];
+		let mut map = Map {
+			heights: Grid([[Height::PIT; 16]; 16]),
+			prefabs: Grid([[Prefab::None; 16]; 16]),
+		};
+		for (y, row) in heights.iter_mut().enumerate() {
+			for (x, height) in row.iter_mut().enumerate() {
+				let spot = y.min(places - 1) * places + x.min(places - 1);
+				let c = cell(spot, x, y);
+				map.prefabs.0[y][x] = c.prefab;
+				*height = c.height.map(|h| h as i32 + lift[spot].unwrap());
+			}
+		}
+		let low = heights
+			.iter()
+			.flatten()
+			.flatten()
+			.min()
+			.copied()
+			.unwrap_or(0);
+		for (y, row) in heights.iter().enumerate() {
+			for (x, height) in row.iter().enumerate() {
+				if let Some(h) = height {
+					let h = (h - low + self.base as i32)
+						.clamp(Height::PIT.0 as i32 + 1, Height::MAX.0 as i32);
+					map.heights.0[y][x] = Height(h as i8);
+				}
+			}
+		}
+		map
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{generate, GenerateOptions, Parsable};
+
+	/// Stripes of raised tiles with jump pads on them, above a pit row.
+	fn striped() -> Map {
+		let mut map = Map::default();
+		for y in 0..16 {
+			for x in 0..16 {
+				map.heights.0[y][x] = Height(if x % 4 < 2 { 3 } else { 1 });
+			}
+		}
+		for x in 0..16 {
+			map.heights.0[15][x] = Height::PIT;
+		}
+		map.prefabs.0[5][4] = Prefab::JumpPad;
+		map
+	}
+
+	#[test]
+	fn options() {
+		assert_eq!(
+			Wfc::learn(&[], &WfcOptions::default()).unwrap_err(),
+			WfcError::NoExamples
+		);
+		let options = WfcOptions {
+			size: 4,
+			..WfcOptions::default()
+		};
+		assert_eq!(
+			Wfc::learn(&[Map::default()], &options).unwrap_err(),
+			WfcError::InvalidSize(4)
+		);
+		let flat = Wfc::learn(&[Map::default()], &WfcOptions::default()).unwrap();
+		assert_eq!(flat.pattern_count(), 1);
+		assert_eq!(flat.generate(0), Ok(Map::default()));
+	}
+
+	#[test]
+	fn relative_heights() {
+		let mut low = Map::default();
+		low.heights.0[4][4] = Height(2);
+		let mut high = low;
+		for h in high.heights.0.iter_mut().flatten() {
+			h.0 += 5;
+		}
+		let patterns = |map: &Map| {
+			Wfc::learn(
+				&[*map],
+				&WfcOptions {
+					symmetries: false,
+					..WfcOptions::default()
+				},
+			)
+			.unwrap()
+			.patterns
+		};
+		assert_eq!(patterns(&low), patterns(&high));
+	}
+
+	#[test]
+	fn style() {
+		let example = striped();
+		for size in 2..=3 {
+			let wfc = Wfc::learn(
+				&[example],
+				&WfcOptions {
+					size,
+					symmetries: false,
+					..WfcOptions::default()
+				},
+			)
+			.unwrap();
+			let map = wfc.generate(size as u64).unwrap();
+			assert_eq!(map, wfc.generate(size as u64).unwrap());
+			// only ever steps of 2 across, flat down, pits at the bottom
+			for ((x, y), h) in map.heights.iter() {
+				if h.is_pit() {
+					assert_eq!(y, 15);
+					continue;
+				}
+				if x > 0 && !map.heights.0[y][x - 1].is_pit() {
+					let step = (h.0 - map.heights.0[y][x - 1].0).abs();
+					assert!(step == 0 || step == 2, "{:?}", (x, y));
+				}
+				if y > 0 {
+					assert_eq!(*h, map.heights.0[y - 1][x], "{:?}", (x, y));
+				}
+			}
+			assert_eq!(
+				map
+					.heights
+					.iter()
+					.map(|(_, h)| h.0)
+					.filter(|h| *h > 0)
+					.min(),
+				Some(1)
+			);
+		}
+	}
+
+	#[test]
+	fn generated_examples() {
+		let examples: Vec<_> = (0..4)
+			.map(|seed| {
+				generate(&GenerateOptions {
+					seed,
+					..GenerateOptions::default()
+				})
+			})
+			.collect();
+		let wfc = Wfc::learn(&examples, &WfcOptions::default()).unwrap();
+		assert!(wfc.pattern_count() > 1000);
+		let map = wfc.generate(0).unwrap();
+		// every part of the map is a part of some example
+		for y in 0..=13 {
+			for x in 0..=13 {
+				let pattern = Wfc::pattern_at(&map, 3, x, y);
+				assert!(wfc.patterns.contains(&pattern), "{:?}", (x, y));
+			}
+		}
+	}
+}
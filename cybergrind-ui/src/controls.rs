@@ -3,7 +3,7 @@ use cybergrind_core::Prefab;
 
 use crate::{
 	history::{Edit, EditData},
-	map3d::{MapResource, Pillar},
+	map3d::Pillar,
 	selection::Selectable,
 };
 
@@ -103,7 +103,6 @@ pub fn number_edit(
 pub fn prefab_edit(
 	key: Res<Input<KeyCode>>,
 	mut edit_events: EventWriter<Edit>,
-	map: Res<MapResource>,
 	query: Query<(&Selectable, &Pillar)>,
 ) {
 	for pressed in key.get_just_pressed() {
@@ -119,16 +118,14 @@ pub fn prefab_edit(
 		};
 		println!("Button press for setting prefab {:?}", prefab);
 
-		let (squares, from): (Vec<(usize, usize)>, Vec<Prefab>) = query
+		let squares = query
 			.iter()
 			.filter(|(s, _)| s.selected())
-			.map(|(_, Pillar(x, y))| {
-				((*x, *y), map.0.prefabs.get(*x, *y).unwrap().clone())
-			})
-			.unzip();
+			.map(|(_, Pillar(x, y))| (*x, *y))
+			.collect::<Vec<(usize, usize)>>();
 
 		edit_events.send(Edit {
-			data: EditData::Prefab { from, to: prefab },
+			data: EditData::Prefab(prefab),
 			squares,
 		});
 	}
@@ -1,13 +1,21 @@
 use std::fs::{File, OpenOptions};
-use std::io::{prelude::*, SeekFrom};
+use std::io::{prelude::*, BufReader, BufWriter, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
 
 use bevy::prelude::*;
-use cybergrind_core::{Map, Parsable};
+use cybergrind_core::{mesh, Map, Metadata, Pack, PackError, Parsable};
 
 use crate::map3d::MapResource;
 pub struct LoadedFile {
 	pub file: Option<(File, String)>,
 	pub unsaved_changes: bool,
+	/// Kept next to the file, see [`cybergrind_core::document`].
+	pub metadata: Metadata,
+	/// A pack that was opened, and its path, while a pattern is picked from
+	/// it.
+	pub pack: Option<(String, Pack)>,
 }
 
 impl LoadedFile {
@@ -37,6 +45,8 @@ impl FromWorld for LoadedFile {
 		Self {
 			file: None,
 			unsaved_changes: false,
+			metadata: Metadata::default(),
+			pack: None,
 		}
 	}
 }
@@ -47,6 +57,7 @@ pub enum FileEvent {
 	Save,
 	SaveAs,
 	New,
+	Export,
 }
 
 fn file_event_handler_system(
@@ -61,6 +72,16 @@ fn file_event_handler_system(
 		if let nfd::Response::Okay(path) =
 			nfd::dialog().open().expect("Error opening file dialog")
 		{
+			if path.ends_with(".cgpack") {
+				match File::open(&path)
+					.map_err(PackError::Io)
+					.and_then(|file| Pack::read(BufReader::new(file)))
+				{
+					Ok(pack) => loaded_file.pack = Some((path, pack)),
+					Err(err) => println!("Error opening pack: {}", err),
+				}
+				return;
+			}
 			let file = match OpenOptions::new()
 				.read(true)
 				.write(true)
@@ -88,6 +109,13 @@ fn file_event_handler_system(
 					return;
 				}
 			};
+			loaded_file.metadata = match Metadata::read(Path::new(&path)) {
+				Ok(metadata) => metadata,
+				Err(err) => {
+					println!("Error reading metadata: {}", err);
+					Metadata::default()
+				}
+			};
 			loaded_file.file = Some((file, path));
 			loaded_file.unsaved_changes = false;
 		}
@@ -97,16 +125,25 @@ fn file_event_handler_system(
 		map.0 = Map::default();
 		loaded_file.file = None;
 		loaded_file.unsaved_changes = false;
+		loaded_file.metadata = Metadata::default();
 	}
 
 	fn save(loaded_file: &mut LoadedFile, map: &mut MapResource) {
 		println!("File event save");
-		if let Some((file, _)) = &mut loaded_file.file {
+		if let Some((file, path)) = &mut loaded_file.file {
+			// Truncated first, a shorter map would otherwise leave the end of
+			// the old one behind it.
 			if let Err(error) = file
-				.seek(SeekFrom::Start(0))
-				.and_then(|_| file.write(map.0.to_string().as_bytes()))
+				.set_len(0)
+				.and_then(|_| file.seek(SeekFrom::Start(0)))
+				.and_then(|_| file.write_all(map.0.to_string().as_bytes()))
 			{
 				println!("Error saving file: {}", error);
+				return;
+			}
+			loaded_file.metadata.touch(SystemTime::now());
+			if let Err(error) = loaded_file.metadata.write(Path::new(path.as_str())) {
+				println!("Error saving metadata: {}", error);
 			} else {
 				println!("Saved file!");
 				loaded_file.unsaved_changes = false;
@@ -136,12 +173,41 @@ fn file_event_handler_system(
 		}
 	}
 
+	fn export(_: &mut LoadedFile, map: &mut MapResource) {
+		println!("File event export");
+		if let nfd::Response::Okay(path) =
+			nfd::open_save_dialog(Some("obj,glb"), None)
+				.expect("Error opening file dialog")
+		{
+			let path = Path::new(&path);
+			let meshes = map.0.to_meshes();
+			let result = if path.extension().map_or(false, |e| e == "glb") {
+				File::create(path)
+					.and_then(|file| mesh::write_glb(&meshes, BufWriter::new(file)))
+			} else {
+				let mtl = path.with_extension("mtl");
+				let mtl_name = mtl.file_name().unwrap().to_string_lossy();
+				File::create(path)
+					.and_then(|file| {
+						mesh::write_obj(&meshes, Some(&mtl_name), BufWriter::new(file))
+					})
+					.and_then(|_| File::create(&mtl))
+					.and_then(|file| mesh::write_mtl(&meshes, BufWriter::new(file)))
+			};
+			match result {
+				Ok(()) => println!("Exported {}", path.display()),
+				Err(err) => println!("Error exporting: {}", err),
+			}
+		}
+	}
+
 	for event in ev_files.iter() {
 		let h = match event {
 			FileEvent::Open => open,
 			FileEvent::Save => save,
 			FileEvent::SaveAs => save_as,
 			FileEvent::New => new,
+			FileEvent::Export => export,
 		};
 		h(&mut loaded_file, &mut map);
 		if let Some(win) = windows.get_primary_mut() {
@@ -1,12 +1,16 @@
 use bevy::prelude::*;
-use cybergrind_core::{Map, Prefab};
+use cybergrind_core::{Filter, Map, MapDiff, Mask, Prefab};
 
 use crate::{files::LoadedFile, map3d::MapResource};
 
 #[derive(Clone, Debug)]
 pub enum EditData {
 	Height(i8),
-	Prefab { from: Vec<Prefab>, to: Prefab },
+	Prefab(Prefab),
+	/// Runs a terrain filter over the squares.
+	Filter(Filter),
+	/// Swaps the whole pattern for another, ignoring the squares.
+	Replace(Map),
 }
 
 #[derive(Clone, Debug)]
@@ -16,49 +20,34 @@ pub struct Edit {
 }
 
 impl Edit {
-	pub fn apply(&self, map: &mut Map) -> bool {
+	pub fn apply(&self, map: &mut Map) {
 		match &self.data {
 			EditData::Height(h) => {
-				if *h == 0i8 {
-					false
-				} else {
-					for (x, y) in self.squares.iter() {
-						if let Some(height) = map.heights.get_mut(*x, *y) {
-							height.0 = (height.0 + h).clamp(-50, 50);
-						}
+				for (x, y) in self.squares.iter() {
+					if let Some(height) = map.heights.get_mut(*x, *y) {
+						*height = height.saturating_add(*h);
 					}
-					true
 				}
 			}
-			EditData::Prefab { from, to } => {
-				if from.iter().all(|f| *f == *to) {
-					false
-				} else {
-					for (x, y) in self.squares.iter() {
-						if let Some(prefab) = map.prefabs.get_mut(*x, *y) {
-							*prefab = *to;
-						}
+			EditData::Prefab(to) => {
+				for (x, y) in self.squares.iter() {
+					if let Some(prefab) = map.prefabs.get_mut(*x, *y) {
+						*prefab = *to;
 					}
-					true
 				}
 			}
-		}
-	}
-
-	pub fn undo(&self, map: &mut Map) {
-		for (i, (x, y)) in self.squares.iter().enumerate() {
-			match &self.data {
-				EditData::Height(h) => {
-					if let Some(height) = map.heights.get_mut(*x, *y) {
-						height.0 = (height.0 - h).clamp(-50, 50);
-					}
-				}
-				EditData::Prefab { from, .. } => {
-					if let Some(prefab) = map.prefabs.get_mut(*x, *y) {
-						*prefab = from[i];
+			EditData::Filter(filter) => {
+				let mut mask: Mask = [[false; 16]; 16];
+				for (x, y) in self.squares.iter() {
+					if let Some(row) = mask.get_mut(*y) {
+						if let Some(selected) = row.get_mut(*x) {
+							*selected = true;
+						}
 					}
 				}
+				map.heights = map.heights.filter(*filter, Some(&mask));
 			}
+			EditData::Replace(to) => *map = *to,
 		}
 	}
 }
@@ -66,7 +55,7 @@ impl Edit {
 static MAX_UNDO_HISTORY: usize = 500;
 
 pub struct HistoryStack {
-	pub stack: Vec<Edit>,
+	pub stack: Vec<MapDiff>,
 	pub pos: usize,
 }
 
@@ -78,11 +67,14 @@ impl HistoryStack {
 		loaded_file: &mut LoadedFile,
 		window: Option<&mut Window>,
 	) {
-		if edit.apply(map) {
+		let before = *map;
+		edit.apply(map);
+		let diff = before.diff(map);
+		if !diff.is_empty() {
 			if self.stack.len() >= MAX_UNDO_HISTORY {
 				self.stack.remove(0);
 			}
-			self.stack.push(edit);
+			self.stack.push(diff);
 			loaded_file.unsaved_changes = true;
 			if let Some(win) = window {
 				win.set_title(loaded_file.window_title());
@@ -99,7 +91,7 @@ impl HistoryStack {
 		window: Option<&mut Window>,
 	) {
 		if let Some(pop) = self.stack.pop() {
-			pop.undo(map);
+			pop.invert().apply(map);
 			loaded_file.unsaved_changes = true;
 			if let Some(win) = window {
 				win.set_title(loaded_file.window_title());
@@ -20,7 +20,11 @@ use smooth_bevy_cameras::{
 };
 use ui::{
 	dialog::{dialog_system_set, DialogDispatch},
-	setup_ui, ui_system_set, ButtonMaterials, MenuButtonKind,
+	filter::{filter_system_set, FilterWindow},
+	pack::pack_system_set,
+	setup_ui,
+	share::{share_system_set, ShareWindow},
+	ui_system_set, ButtonMaterials, MenuButtonKind,
 };
 
 mod controls;
@@ -73,6 +77,8 @@ fn main() {
 		.add_plugin(HistoryPlugin)
 		.init_resource::<LoadedFile>()
 		.init_resource::<ButtonMaterials>()
+		.init_resource::<ShareWindow>()
+		.init_resource::<FilterWindow>()
 		.add_startup_system(setup.system())
 		.add_startup_system(spawn_map.system())
 		.add_startup_system(setup_ui.system())
@@ -85,6 +91,9 @@ fn main() {
 		.add_system(draw_grid.system())
 		.add_system_set(ui_system_set())
 		.add_system_set(dialog_system_set())
+		.add_system_set(share_system_set())
+		.add_system_set(pack_system_set())
+		.add_system_set(filter_system_set())
 		.add_system_set(files_system_set())
 		.add_system_set(controls_system_set())
 		.run();
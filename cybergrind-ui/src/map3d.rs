@@ -1,6 +1,6 @@
 use bevy::{prelude::*, render::texture::ImageType};
 use bevy_mod_raycast::RayCastMesh;
-use cybergrind_core::{Map, Prefab};
+use cybergrind_core::{Map, Prefab, PREFAB_ATLAS};
 
 use crate::selection::{Selectable, SelectableRaycastSet};
 
@@ -29,18 +29,14 @@ pub fn spawn_map(
 	let box_material = materials.add(textures.add(box_texture).into());
 
 	let prefabs_texture = textures.add(
-		Texture::from_buffer(
-			include_bytes!("../assets/prefabs.png"),
-			ImageType::Extension("png"),
-		)
-		.unwrap(),
+		Texture::from_buffer(PREFAB_ATLAS, ImageType::Extension("png")).unwrap(),
 	);
 
 	let prefabs_atlas = atlases.add(TextureAtlas::from_grid(
 		prefabs_texture,
 		Vec2::new(16.0, 16.0),
 		1,
-		5,
+		6,
 	));
 	commands.insert_resource(PrefabAtlas(prefabs_atlas.clone()));
 
@@ -123,7 +119,7 @@ pub fn update_map_display(
 ) {
 	if map.is_changed() {
 		for (pillar, mut transform) in query.iter_mut() {
-			let height = map.0.heights.0[pillar.1][pillar.0].0;
+			let height = map.0.heights.0[pillar.1][pillar.0].get();
 			transform.translation.x = pillar.0 as f32 * BOX_SCALE;
 			transform.translation.z = pillar.1 as f32 * BOX_SCALE;
 			transform.translation.y = height as f32 * BOX_SCALE;
@@ -150,6 +146,7 @@ pub fn update_prefabs(
 					Prefab::JumpPad => 2,
 					Prefab::Stairs => 1,
 					Prefab::Hideous => 0,
+					Prefab::Unknown(_) => 5,
 				};
 				visible.is_visible = prefab != &Prefab::None;
 			}
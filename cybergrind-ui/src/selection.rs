@@ -93,7 +93,7 @@ fn raycast_handle_mouse(
 	for pick_source in pick_source_query.iter() {
 		if let Some((entity, _)) = pick_source.intersect_top() {
 			if let Ok((Pillar(x, y), mut selectable, _)) = query.get_mut(entity) {
-				let h = map.0.heights.get(*x, *y).unwrap().0;
+				let h = map.0.heights.get(*x, *y).unwrap().get();
 				// let center =
 				// 	Vec3::new(*x as f32, h as f32, *y as f32) - Vec3::splat(7.5);
 				// let offset = Vec3::new(1.0, 16.0, 1.0);
@@ -12,6 +12,9 @@ use crate::{
 use self::dialog::setup_dialog;
 
 pub mod dialog;
+pub mod filter;
+pub mod pack;
+pub mod share;
 
 static HELP_TEXT: &'static str = r#"Q: None
 W: Melee
@@ -23,6 +26,8 @@ Ctrl + Z: Undo
 Ctrl + N: New
 Ctrl + S: Save
 Ctrl + A: Save As
+Ctrl + X: Export
+Ctrl + H: Share Code
 
 Alt: Rotate
 Alt + Scroll: Zoom"#;
@@ -75,9 +80,11 @@ pub enum MenuButtonKind {
 	Open,
 	Save,
 	SaveAs,
+	Export,
+	Share,
 }
 
-const MENU_BUTTONS: [MenuButton; 4] = [
+const MENU_BUTTONS: [MenuButton; 6] = [
 	MenuButton {
 		kind: MenuButtonKind::New,
 		name: "(N)ew",
@@ -98,6 +105,16 @@ const MENU_BUTTONS: [MenuButton; 4] = [
 		name: "Save (A)s",
 		key_code: KeyCode::A,
 	},
+	MenuButton {
+		kind: MenuButtonKind::Export,
+		name: "E(x)port",
+		key_code: KeyCode::X,
+	},
+	MenuButton {
+		kind: MenuButtonKind::Share,
+		name: "S(h)are",
+		key_code: KeyCode::H,
+	},
 ];
 
 pub fn setup_ui(
@@ -308,6 +325,11 @@ fn menu_button_handler_system(
 				MenuButtonKind::SaveAs => {
 					ev_files.send(FileEvent::SaveAs);
 				}
+				MenuButtonKind::Export => {
+					ev_files.send(FileEvent::Export);
+				}
+				// Opens its own window, see `share_button_system`.
+				MenuButtonKind::Share => {}
 			};
 		} else {
 			ev_files.send(match ev {
@@ -315,6 +337,8 @@ fn menu_button_handler_system(
 				MenuButtonKind::Save => FileEvent::Save,
 				MenuButtonKind::New => FileEvent::New,
 				MenuButtonKind::SaveAs => FileEvent::SaveAs,
+				MenuButtonKind::Export => FileEvent::Export,
+				MenuButtonKind::Share => continue,
 			})
 		}
 	}
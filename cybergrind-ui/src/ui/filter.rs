@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use cybergrind_core::{Filter, Height};
+
+use crate::{
+	history::{Edit, EditData},
+	map3d::Pillar,
+	selection::Selectable,
+};
+
+/// The settings of each filter, kept between uses.
+pub struct FilterWindow {
+	radius: usize,
+	talus: u8,
+	iterations: usize,
+	levels: u8,
+	step: u8,
+	min: i8,
+	max: i8,
+}
+
+impl Default for FilterWindow {
+	fn default() -> Self {
+		Self {
+			radius: 1,
+			talus: 2,
+			iterations: 10,
+			levels: 4,
+			step: 2,
+			min: 0,
+			max: 10,
+		}
+	}
+}
+
+/// Runs terrain filters over the selected squares.
+fn filter_window_system(
+	egui_ctx: Res<EguiContext>,
+	mut settings: ResMut<FilterWindow>,
+	mut edit_events: EventWriter<Edit>,
+	query: Query<(&Selectable, &Pillar)>,
+) {
+	let mut picked = None;
+	egui::Window::new("Terrain Filters")
+		.anchor(egui::Align2::RIGHT_TOP, egui::Vec2::new(-10.0, 25.0))
+		.resizable(false)
+		.show(egui_ctx.ctx(), |ui| {
+			ui.label("Applies to the selected squares.");
+			ui.separator();
+			ui.add(egui::Slider::new(&mut settings.radius, 1..=4).text("radius"));
+			ui.horizontal(|ui| {
+				if ui.button("Smooth").clicked() {
+					picked = Some(Filter::Smooth {
+						radius: settings.radius,
+					});
+				}
+				if ui.button("Median").clicked() {
+					picked = Some(Filter::Median {
+						radius: settings.radius,
+					});
+				}
+			});
+			ui.separator();
+			ui.add(egui::Slider::new(&mut settings.talus, 0..=10).text("talus"));
+			ui.add(
+				egui::Slider::new(&mut settings.iterations, 1..=50).text("passes"),
+			);
+			if ui.button("Erode").clicked() {
+				picked = Some(Filter::Erode {
+					talus: settings.talus,
+					iterations: settings.iterations,
+				});
+			}
+			ui.separator();
+			ui.add(egui::Slider::new(&mut settings.levels, 1..=10).text("levels"));
+			if ui.button("Terrace").clicked() {
+				picked = Some(Filter::Terrace {
+					levels: settings.levels,
+				});
+			}
+			ui.add(egui::Slider::new(&mut settings.step, 1..=10).text("step"));
+			if ui.button("Quantize").clicked() {
+				picked = Some(Filter::Quantize {
+					step: settings.step,
+				});
+			}
+			ui.separator();
+			let range = Height::MIN.get()..=Height::MAX.get();
+			ui.add(egui::Slider::new(&mut settings.min, range.clone()).text("min"));
+			ui.add(egui::Slider::new(&mut settings.max, range).text("max"));
+			ui.horizontal(|ui| {
+				if ui.button("Normalize").clicked() {
+					picked = Some(Filter::Normalize {
+						min: settings.min,
+						max: settings.max,
+					});
+				}
+				if ui.button("Invert").clicked() {
+					picked = Some(Filter::Invert);
+				}
+			});
+		});
+
+	if let Some(filter) = picked {
+		let squares = query
+			.iter()
+			.filter(|(s, _)| s.selected())
+			.map(|(_, Pillar(x, y))| (*x, *y))
+			.collect::<Vec<(usize, usize)>>();
+
+		edit_events.send(Edit {
+			data: EditData::Filter(filter),
+			squares,
+		});
+	}
+}
+
+pub fn filter_system_set() -> SystemSet {
+	SystemSet::new().with_system(filter_window_system.system())
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{files::LoadedFile, map3d::MapResource};
+
+/// Lists the patterns of the pack the user opened, loading the one they pick.
+fn pack_window_system(
+	egui_ctx: Res<EguiContext>,
+	mut windows: ResMut<Windows>,
+	mut map: ResMut<MapResource>,
+	mut loaded_file: ResMut<LoadedFile>,
+) {
+	let (path, pack) = match &loaded_file.pack {
+		Some(pack) => pack,
+		None => return,
+	};
+	let mut open = true;
+	let mut picked = None;
+	egui::Window::new("Open From Pack")
+		.open(&mut open)
+		.collapsible(false)
+		.anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+		.show(egui_ctx.ctx(), |ui| {
+			ui.label(format!("Pick a pattern from {}.", path));
+			egui::ScrollArea::auto_sized().show(ui, |ui| {
+				for entry in pack.entries() {
+					let metadata = &entry.document.metadata;
+					let mut label = entry.name.clone();
+					if !metadata.title.is_empty() {
+						label += &format!(": {}", metadata.title);
+					}
+					if !metadata.author.is_empty() {
+						label += &format!(" by {}", metadata.author);
+					}
+					if ui.button(label).clicked() {
+						picked = Some(entry.document.clone());
+					}
+				}
+			});
+		});
+	if let Some(document) = picked {
+		// Patterns can't be saved back into packs, so the picked one starts
+		// out as a new, unsaved file.
+		map.0 = document.map;
+		loaded_file.file = None;
+		loaded_file.metadata = document.metadata;
+		loaded_file.unsaved_changes = true;
+		loaded_file.pack = None;
+		if let Some(win) = windows.get_primary_mut() {
+			win.set_title(loaded_file.window_title());
+		}
+	} else if !open {
+		loaded_file.pack = None;
+	}
+}
+
+pub fn pack_system_set() -> SystemSet {
+	SystemSet::new().with_system(pack_window_system.system())
+}
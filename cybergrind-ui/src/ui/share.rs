@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use cybergrind_core::Map;
+
+use crate::{
+	history::{Edit, EditData},
+	map3d::MapResource,
+	ui::MenuButtonKind,
+};
+
+/// The window for copying the pattern as a share code, or pasting one in.
+#[derive(Default)]
+pub struct ShareWindow {
+	pub open: bool,
+	pub code: String,
+	pub error: Option<String>,
+}
+
+fn share_button_system(
+	mut ev_menu_button: EventReader<MenuButtonKind>,
+	map: Res<MapResource>,
+	mut share: ResMut<ShareWindow>,
+) {
+	for ev in ev_menu_button.iter() {
+		if let MenuButtonKind::Share = ev {
+			*share = ShareWindow {
+				open: true,
+				code: map.0.to_share_code(),
+				error: None,
+			};
+		}
+	}
+}
+
+fn share_window_system(
+	egui_ctx: Res<EguiContext>,
+	mut share: ResMut<ShareWindow>,
+	map: Res<MapResource>,
+	mut edit_events: EventWriter<Edit>,
+) {
+	if !share.open {
+		return;
+	}
+	let mut open = true;
+	egui::Window::new("Share Code")
+		.open(&mut open)
+		.collapsible(false)
+		.anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+		.show(egui_ctx.ctx(), |ui| {
+			ui.label("Copy this pattern's code, or paste one in and load it.");
+			ui.text_edit_multiline(&mut share.code);
+			ui.horizontal(|ui| {
+				if ui.button("Copy").clicked() {
+					share.code = map.0.to_share_code();
+					share.error = None;
+					ui.output().copied_text = share.code.clone();
+				}
+				if ui.button("Load").clicked() {
+					match Map::from_share_code(&share.code) {
+						Ok(shared) => {
+							// through the history, so it can be undone
+							edit_events.send(Edit {
+								data: EditData::Replace(shared),
+								squares: Vec::new(),
+							});
+							share.error = None;
+						}
+						Err(err) => share.error = Some(err.to_string()),
+					}
+				}
+			});
+			if let Some(error) = &share.error {
+				ui.colored_label(egui::Color32::RED, error);
+			}
+		});
+	share.open = open;
+}
+
+pub fn share_system_set() -> SystemSet {
+	SystemSet::new()
+		.with_system(share_button_system.system())
+		.with_system(share_window_system.system())
+}